@@ -0,0 +1,117 @@
+//! Compares the boxed and arena representations of a large program,
+//! by the allocations and time taken to parse it into each, traverse, and drop them.
+//!
+//! The arena is converted from the boxed tree the parser builds,
+//! so parsing into it costs the boxed allocations and then some;
+//! it is only cheaper to traverse and drop.
+//!
+//! Run with `cargo bench --features arena --bench arena`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use lynx::{
+    arena::{ArenaExpr, ExprArena, ExprId},
+    ast::Expr,
+    lexer::tokenize,
+    parser::{ParserConfig, parse},
+};
+
+/// Lexes and parses Lynx source into boxed expressions.
+fn parse_boxed(src: &str) -> Vec<Expr> {
+    parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap()
+}
+
+/// Number of top-level expressions in the generated program.
+const EXPR_COUNT: usize = 20_000;
+
+/// System allocator counting the allocations made.
+struct CountingAlloc;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Runs `f`, printing the allocations and time it took under `label`.
+fn measure<T>(label: &str, f: impl FnOnce() -> T) -> T {
+    let allocs = ALLOCS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let allocs = ALLOCS.load(Ordering::Relaxed) - allocs;
+    println!("{:<16} {:>10} allocations {:>12?}", label, allocs, elapsed);
+    result
+}
+
+/// Counts the nodes of a boxed expression.
+fn count_boxed(expr: &Expr) -> usize {
+    1 + match expr {
+        Expr::Atom(..) => 0,
+        Expr::App(func, arg, _) => count_boxed(func) + count_boxed(arg),
+        Expr::Tuple(elems, _) | Expr::List(elems, _) | Expr::Block(elems, _) => {
+            elems.iter().map(count_boxed).sum()
+        }
+    }
+}
+
+/// Counts the nodes of an expression in the arena.
+fn count_arena(arena: &ExprArena, id: ExprId) -> usize {
+    1 + match &arena[id] {
+        ArenaExpr::Atom(..) => 0,
+        ArenaExpr::App(func, arg, _) => count_arena(arena, *func) + count_arena(arena, *arg),
+        ArenaExpr::Tuple(ids, _) | ArenaExpr::List(ids, _) | ArenaExpr::Block(ids, _) => arena
+            .elems(*ids)
+            .iter()
+            .map(|&id| count_arena(arena, id))
+            .sum(),
+    }
+}
+
+fn main() {
+    let src: String = (0..EXPR_COUNT)
+        .map(|idx| {
+            format!(
+                "x{} = f (a, [1, 2, {}]) + g {{b; c}} * m.h (-{});\n",
+                idx, idx, idx
+            )
+        })
+        .collect();
+
+    println!("-- boxed");
+    let boxed = measure("parse", || parse_boxed(&src));
+    let count = measure("traverse", || boxed.iter().map(count_boxed).sum::<usize>());
+    measure("drop", || drop(black_box(boxed)));
+
+    println!("-- arena");
+    let (arena, ids) = measure("parse", || {
+        let mut arena = ExprArena::new();
+        let ids: Vec<_> = parse_boxed(&src)
+            .iter()
+            .map(|expr| arena.alloc(expr))
+            .collect();
+        (arena, ids)
+    });
+    let arena_count = measure("traverse", || {
+        ids.iter().map(|&id| count_arena(&arena, id)).sum::<usize>()
+    });
+    measure("drop", || drop(black_box(arena)));
+
+    assert_eq!(count, arena_count);
+    println!("{} nodes", count);
+}
@@ -0,0 +1,14 @@
+//! Checks that lexing and parsing never panic, whatever the input.
+//!
+//! Run with `cargo +nightly fuzz run parse` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lynx::parser::parse_source;
+
+fuzz_target!(|src: &str| {
+    let (module, diags) = parse_source(src);
+    // Whatever fails is reported rather than dropped
+    assert!(module.is_some() || !diags.is_empty());
+});
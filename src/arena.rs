@@ -0,0 +1,196 @@
+use std::ops::Index;
+
+use crate::{
+    ast::{AtomKind, Expr},
+    token::Span,
+};
+
+/// Index of an expression in [`ExprArena`].
+///
+/// Ids are ordered by allocation, so children come before their parents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExprId(usize);
+
+/// Run of consecutive [`ExprId`]s in [`ExprArena`],
+/// holding the elements of a tuple, list, or block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExprIds {
+    /// Index of the first id.
+    start: usize,
+    /// Number of ids.
+    len: usize,
+}
+
+/// Lynx expression stored in [`ExprArena`].
+///
+/// This mirrors [`Expr`], with children referred to by [`ExprId`]s instead of boxes.
+#[derive(Debug, Clone)]
+pub enum ArenaExpr {
+    /// Atom, i.e. a literal, the wildcard, or a name.
+    Atom(AtomKind, Span),
+    /// Function application.
+    App(ExprId, ExprId, Span),
+    /// Tuple, e.g. `a, b, c`.
+    Tuple(ExprIds, Span),
+    /// List, e.g. `[a, b, c]`.
+    List(ExprIds, Span),
+    /// Block, e.g. `{a; b; c}`.
+    Block(ExprIds, Span),
+}
+
+impl ArenaExpr {
+    /// Returns the position of the expression in Lynx source.
+    pub fn span(&self) -> Span {
+        match self {
+            ArenaExpr::Atom(_, span)
+            | ArenaExpr::App(_, _, span)
+            | ArenaExpr::Tuple(_, span)
+            | ArenaExpr::List(_, span)
+            | ArenaExpr::Block(_, span) => *span,
+        }
+    }
+}
+
+/// Arena holding Lynx expressions in flat [`Vec`]s,
+/// an alternative to the boxed [`Expr`] for large programs.
+///
+/// Nodes are allocated in bulk rather than one box at a time,
+/// which improves locality, and dropping the arena frees them all at once.
+/// Children are allocated before their parents,
+/// so a tree is converted in a single pass.
+///
+/// The arena is only built by converting a boxed tree, see [`Self::alloc`]:
+/// the parser still builds the boxed tree first,
+/// so parsing into the arena makes more allocations, not fewer.
+/// It pays off for trees kept around and traversed repeatedly, e.g. by tools.
+#[derive(Debug, Default)]
+pub struct ExprArena {
+    /// All expressions allocated so far.
+    exprs: Vec<ArenaExpr>,
+
+    /// Elements of all tuples, lists, and blocks, as runs of ids.
+    elems: Vec<ExprId>,
+}
+
+impl ExprArena {
+    /// Creates an empty [`ExprArena`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of expressions allocated.
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    /// Checks if no expression has been allocated.
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /// Returns the ids of the elements in a run.
+    pub fn elems(&self, ids: ExprIds) -> &[ExprId] {
+        &self.elems[ids.start..ids.start + ids.len]
+    }
+
+    /// Allocates a copy of the boxed expression, returning the id of its root.
+    ///
+    /// The boxed expression is left as is, to be dropped by the caller.
+    pub fn alloc(&mut self, expr: &Expr) -> ExprId {
+        let arena_expr = match expr {
+            Expr::Atom(kind, span) => ArenaExpr::Atom(kind.clone(), *span),
+            Expr::App(func, arg, span) => {
+                let func = self.alloc(func);
+                let arg = self.alloc(arg);
+                ArenaExpr::App(func, arg, *span)
+            }
+            Expr::Tuple(elems, span) => ArenaExpr::Tuple(self.alloc_elems(elems), *span),
+            Expr::List(elems, span) => ArenaExpr::List(self.alloc_elems(elems), *span),
+            Expr::Block(elems, span) => ArenaExpr::Block(self.alloc_elems(elems), *span),
+        };
+        self.exprs.push(arena_expr);
+        ExprId(self.exprs.len() - 1)
+    }
+
+    /// Allocates copies of the elements of a tuple, list, or block,
+    /// returning the run of their ids.
+    fn alloc_elems(&mut self, elems: &[Expr]) -> ExprIds {
+        // The elements' own children are allocated in between,
+        // so their ids are gathered before being stored as a run
+        let ids: Vec<_> = elems.iter().map(|elem| self.alloc(elem)).collect();
+        let start = self.elems.len();
+        self.elems.extend(ids);
+        ExprIds {
+            start,
+            len: elems.len(),
+        }
+    }
+
+    /// Converts the expression back into the boxed form.
+    pub fn to_expr(&self, id: ExprId) -> Expr {
+        let to_exprs = |ids: ExprIds| self.elems(ids).iter().map(|&id| self.to_expr(id)).collect();
+        match &self[id] {
+            ArenaExpr::Atom(kind, span) => Expr::Atom(kind.clone(), *span),
+            ArenaExpr::App(func, arg, span) => Expr::App(
+                Box::new(self.to_expr(*func)),
+                Box::new(self.to_expr(*arg)),
+                *span,
+            ),
+            ArenaExpr::Tuple(ids, span) => Expr::Tuple(to_exprs(*ids), *span),
+            ArenaExpr::List(ids, span) => Expr::List(to_exprs(*ids), *span),
+            ArenaExpr::Block(ids, span) => Expr::Block(to_exprs(*ids), *span),
+        }
+    }
+}
+
+impl Index<ExprId> for ExprArena {
+    type Output = ArenaExpr;
+
+    fn index(&self, ExprId(idx): ExprId) -> &ArenaExpr {
+        &self.exprs[idx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::ParserConfig};
+
+    fn parse_str(src: &str) -> Vec<Expr> {
+        crate::parser::parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let src = "f (a, [1, 'c'], {x = \"s\"; x}) -> g _;\n\nm.h (-2.5) ()";
+        let exprs = parse_str(src);
+        let mut arena = ExprArena::new();
+        let ids: Vec<_> = exprs.iter().map(|expr| arena.alloc(expr)).collect();
+        for (expr, id) in exprs.iter().zip(ids) {
+            assert_eq!(format!("{:?}", arena.to_expr(id)), format!("{:?}", expr));
+            assert_eq!(arena[id].span(), expr.span());
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let exprs = parse_str("f [a, (b, c)]");
+        let mut arena = ExprArena::new();
+        let root = arena.alloc(&exprs[0]);
+        // `f`, `a`, `b`, `c`, the tuple, the list, and the application
+        assert_eq!(arena.len(), 7);
+
+        let ArenaExpr::App(func, list, _) = arena[root] else {
+            panic!("expected application");
+        };
+        assert!(matches!(&arena[func], ArenaExpr::Atom(AtomKind::Name(name), _) if name == "f"));
+        let ArenaExpr::List(elems, _) = arena[list] else {
+            panic!("expected list");
+        };
+        let elems = arena.elems(elems);
+        assert_eq!(elems.len(), 2);
+        assert!(matches!(arena[elems[1]], ArenaExpr::Tuple(..)));
+        // Children precede their parents
+        assert!(elems.iter().all(|elem| *elem < list));
+    }
+}
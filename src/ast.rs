@@ -1,31 +1,166 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
 
-use crate::token::Span;
+use crate::{
+    parser::{APP_PREC, Assoc, Fixity, OpTable, TUPLE_PREC, is_sym_name},
+    token::{Pos, Span},
+};
 
-#[derive(Debug)]
+/// Lynx expression.
+///
+/// Operators are desugared into applications of their names,
+/// e.g. `a + b` is represented as `((+) a) b`.
+#[derive(Debug, Clone)]
 pub enum Expr {
+    /// Atom, i.e. a literal, the wildcard, or a name.
     Atom(AtomKind, Span),
+    /// Function application.
     App(Box<Expr>, Box<Expr>, Span),
+    /// Tuple, e.g. `a, b, c`.
+    Tuple(Vec<Expr>, Span),
+    /// List, e.g. `[a, b, c]`.
+    List(Vec<Expr>, Span),
+    /// Block, e.g. `{a; b; c}`.
     Block(Vec<Expr>, Span),
 }
 
+impl Expr {
+    /// Returns the position of the expression in Lynx source.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Atom(_, span)
+            | Expr::App(_, _, span)
+            | Expr::Tuple(_, span)
+            | Expr::List(_, span)
+            | Expr::Block(_, span) => *span,
+        }
+    }
+
+    /// Returns the depth of the expression tree,
+    /// e.g. `1` for an atom and `3` for `f x y`.
+    ///
+    /// The tree is walked without recursion, so that trees of any depth can be measured.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((expr, level)) = stack.pop() {
+            depth = depth.max(level);
+            match expr {
+                Expr::Atom(..) => {}
+                Expr::App(func, arg, _) => {
+                    stack.push((func, level + 1));
+                    stack.push((arg, level + 1));
+                }
+                Expr::Tuple(elems, _) | Expr::List(elems, _) | Expr::Block(elems, _) => {
+                    stack.extend(elems.iter().map(|elem| (elem, level + 1)));
+                }
+            }
+        }
+        depth
+    }
+
+    /// Views the expression as an application,
+    /// returning the head and the arguments (left to right),
+    /// e.g. `f` and `[x, y]` for `f x y`.
+    ///
+    /// Expressions other than applications are heads without arguments.
+    pub fn as_application(&self) -> (&Expr, Vec<&Expr>) {
+        let mut head = self;
+        let mut args = Vec::new();
+        while let Expr::App(func, arg, _) = head {
+            args.push(arg.as_ref());
+            head = func;
+        }
+        args.reverse();
+        (head, args)
+    }
+
+    /// Returns the path of nested expressions containing `pos`,
+    /// from `self` down to the innermost one, e.g. for breadcrumbs in editors,
+    /// or an empty path if `self` does not contain `pos`.
+    ///
+    /// Where children overlap, the first one containing `pos` is followed,
+    /// i.e. the function before the argument of an application;
+    /// a child spanning the same text as its parent follows it in the path.
+    pub fn path_at(&self, pos: Pos) -> Vec<&Expr> {
+        let mut path = Vec::new();
+        let mut expr = self;
+        while expr.span().encloses(&Span::point(pos)) {
+            path.push(expr);
+            let next = match expr {
+                Expr::Atom(..) => None,
+                Expr::App(func, arg, _) => [func.as_ref(), arg.as_ref()]
+                    .into_iter()
+                    .find(|child| child.span().encloses(&Span::point(pos))),
+                Expr::Tuple(elems, _) | Expr::List(elems, _) | Expr::Block(elems, _) => elems
+                    .iter()
+                    .find(|child| child.span().encloses(&Span::point(pos))),
+            };
+            let Some(next) = next else {
+                break;
+            };
+            expr = next;
+        }
+        path
+    }
+
+    /// Builds the application of `head` to `args` (left to right),
+    /// the inverse of [`Expr::as_application`].
+    pub fn from_application(head: Expr, args: impl IntoIterator<Item = Expr>) -> Expr {
+        args.into_iter().fold(head, |func, arg| {
+            let span = Span(func.span().0, arg.span().1);
+            Expr::App(Box::new(func), Box::new(arg), span)
+        })
+    }
+}
+
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expr::Atom(atom_type, _) => write!(f, "{}", atom_type),
             Expr::App(func, arg, _) => write!(f, "({} {})", func, arg),
-            Expr::Block(exprs, _) => {
+            Expr::Tuple(exprs, _) => {
+                write!(f, "(")?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", expr)?;
+                }
+                write!(f, ")")
+            }
+            Expr::List(exprs, _) => {
                 write!(f, "[")?;
-                for expr in exprs {
-                    write!(f, "{} ", expr)?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", expr)?;
                 }
                 write!(f, "]")
             }
+            Expr::Block(exprs, _) => {
+                write!(f, "{{")?;
+                for expr in exprs {
+                    write!(f, "{}; ", expr)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
-#[derive(Debug)]
+/// Lynx module, i.e. the top-level expressions of a whole source file.
+#[derive(Debug, Clone)]
+pub struct Module(
+    /// Top-level expressions.
+    pub Vec<Expr>,
+    /// Position in the source, from the first token to the last,
+    /// or [`Span::dummy`] if there are none.
+    pub Span,
+);
+
+/// Kind of an atom.
+#[derive(Debug, Clone)]
 pub enum AtomKind {
     UnitLit,
     IntLit(i64),
@@ -51,3 +186,391 @@ impl Display for AtomKind {
         }
     }
 }
+
+/// Precedence of prefix operator applications, e.g. `-x`,
+/// which bind tighter than infix operators but looser than function application.
+const PREFIX_PREC: u8 = APP_PREC - 1;
+
+/// Precedence of atoms and bracketed expressions.
+const ATOM_PREC: u8 = u8::MAX;
+
+/// Shape of an [`Expr`] as seen by [`Pretty`].
+enum Form<'a> {
+    /// Application of an infix operator.
+    Infix(&'a str, Fixity, &'a Expr, &'a Expr),
+    /// Application of a prefix operator.
+    Prefix(&'a str, &'a Expr),
+    /// Ordinary function application.
+    App(&'a Expr, &'a Expr),
+    /// Anything else.
+    Other,
+}
+
+/// Wrapper rendering an [`Expr`] as Lynx source,
+/// with operators restored and parentheses inserted only where needed.
+///
+/// Created by [`Expr::pretty`].
+pub struct Pretty<'a> {
+    /// Expression to render.
+    expr: &'a Expr,
+
+    /// Operators whose fixities decide the parenthesization.
+    ops: &'a OpTable,
+}
+
+impl Expr {
+    /// Returns a [`Display`] wrapper rendering the expression as Lynx source.
+    pub fn pretty<'a>(&'a self, ops: &'a OpTable) -> Pretty<'a> {
+        Pretty { expr: self, ops }
+    }
+}
+
+impl<'a> Pretty<'a> {
+    /// Returns the shape of an expression.
+    fn form(&self, expr: &'a Expr) -> Form<'a> {
+        let Expr::App(func, arg, _) = expr else {
+            return Form::Other;
+        };
+
+        if let Expr::App(op, lhs, _) = func.as_ref()
+            && let Expr::Atom(AtomKind::Name(op), _) = op.as_ref()
+            && let Some(fixity) = self.ops.infix(op)
+        {
+            return Form::Infix(op, fixity, lhs, arg);
+        }
+
+        if let Expr::Atom(AtomKind::Name(func), _) = func.as_ref()
+            && let Some(op) = self.ops.prefix_op(func)
+        {
+            return Form::Prefix(op, arg);
+        }
+
+        Form::App(func, arg)
+    }
+
+    /// Returns the precedence of an expression.
+    fn prec(&self, expr: &'a Expr) -> u8 {
+        match (self.form(expr), expr) {
+            (Form::Infix(_, Fixity(_, prec), _, _), _) => prec,
+            (Form::Prefix(..), _) => PREFIX_PREC,
+            (Form::App(..), _) => APP_PREC,
+            (Form::Other, Expr::Tuple(..)) => TUPLE_PREC,
+            (Form::Other, _) => ATOM_PREC,
+        }
+    }
+
+    /// Checks if an expression is rendered starting with a character
+    /// that would merge with a preceding prefix operator into one token.
+    fn starts_with_sym(&self, expr: &'a Expr) -> bool {
+        match (self.form(expr), expr) {
+            (Form::Prefix(..), _) => true,
+            (Form::App(func, _), _) => self.starts_with_sym(func),
+            (Form::Infix(_, Fixity(_, prec), lhs, _), _) if prec > APP_PREC => {
+                self.starts_with_sym(lhs)
+            }
+            (_, Expr::Atom(AtomKind::CharLit(_), _)) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if an expression is an alphabetic name or a chain of accesses,
+    /// such as `m.f`, which may be rendered without spaces.
+    fn is_name_chain(&self, expr: &'a Expr) -> bool {
+        match (self.form(expr), expr) {
+            (Form::Infix(_, Fixity(_, prec), lhs, rhs), _) if prec > APP_PREC => {
+                self.is_name_chain(lhs) && self.is_name_chain(rhs)
+            }
+            (_, Expr::Atom(AtomKind::Name(name), _)) => !is_sym_name(name),
+            _ => false,
+        }
+    }
+
+    /// Renders an expression,
+    /// parenthesized if it binds looser than `min_prec`.
+    fn fmt_expr(&self, expr: &'a Expr, min_prec: u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parens = self.prec(expr) < min_prec;
+        if parens {
+            write!(f, "(")?;
+        }
+
+        match self.form(expr) {
+            Form::Infix(op, Fixity(_, prec), lhs, rhs) if prec > APP_PREC => {
+                self.fmt_expr(lhs, prec, f)?;
+                if self.is_name_chain(lhs) && self.is_name_chain(rhs) {
+                    write!(f, "{}", op)?;
+                } else {
+                    write!(f, " {} ", op)?;
+                }
+                self.fmt_expr(rhs, ATOM_PREC, f)?;
+            }
+            Form::Infix(op, Fixity(assoc, prec), lhs, rhs) => {
+                let (lhs_prec, rhs_prec) = match assoc {
+                    Assoc::Left => (prec, prec + 1),
+                    Assoc::Right => (prec + 1, prec),
+                    Assoc::None => (prec + 1, prec + 1),
+                };
+                self.fmt_expr(lhs, lhs_prec, f)?;
+                write!(f, " {} ", op)?;
+                self.fmt_expr(rhs, rhs_prec, f)?;
+            }
+            Form::Prefix(op, operand) => {
+                write!(f, "{}", op)?;
+                if self.starts_with_sym(operand) {
+                    write!(f, "(")?;
+                    self.fmt_expr(operand, 0, f)?;
+                    write!(f, ")")?;
+                } else {
+                    self.fmt_expr(operand, PREFIX_PREC, f)?;
+                }
+            }
+            Form::App(func, arg) => {
+                self.fmt_expr(func, APP_PREC, f)?;
+                write!(f, " ")?;
+                match self.form(arg) {
+                    // Arguments may start with prefix operators that are not also infix
+                    Form::Prefix(op, _) if self.ops.infix(op).is_none() => {
+                        self.fmt_expr(arg, PREFIX_PREC, f)?
+                    }
+                    _ => self.fmt_expr(arg, APP_PREC + 1, f)?,
+                }
+            }
+            Form::Other => self.fmt_other(expr, f)?,
+        }
+
+        if parens {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+
+    /// Renders an atom, a tuple, a list, or a block.
+    fn fmt_other(&self, expr: &'a Expr, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (exprs, open, sep, close, min_prec) = match expr {
+            Expr::Atom(AtomKind::Name(name), _) if is_sym_name(name) => {
+                return write!(f, "({})", name);
+            }
+            Expr::Atom(atom, _) => return write!(f, "{}", atom),
+            Expr::App(..) => return write!(f, "{}", expr),
+            Expr::Tuple(exprs, _) => (exprs, "", ", ", "", TUPLE_PREC + 1),
+            Expr::List(exprs, _) => (exprs, "[", ", ", "]", TUPLE_PREC + 1),
+            Expr::Block(exprs, _) => (exprs, "{", "; ", "}", 0),
+        };
+
+        write!(f, "{}", open)?;
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", sep)?;
+            }
+            self.fmt_expr(expr, min_prec, f)?;
+        }
+        write!(f, "{}", close)
+    }
+}
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_expr(self.expr, 0, f)
+    }
+}
+
+/// Wrapper rendering an [`Expr`] as an indented tree for debugging,
+/// one node per line with its kind and span,
+/// and the atoms, i.e. the tokens, as leaves.
+///
+/// Created by [`Expr::tree`].
+pub struct Tree<'a>(&'a Expr);
+
+impl Expr {
+    /// Returns a [`Display`] wrapper rendering the expression as an indented tree.
+    pub fn tree(&self) -> Tree<'_> {
+        Tree(self)
+    }
+}
+
+impl Tree<'_> {
+    /// Renders a node at `depth` levels of indentation, followed by its children.
+    fn fmt_node(expr: &Expr, depth: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        let (kind, children) = match expr {
+            Expr::Atom(atom, span) => return writeln!(f, "{:?} {}", atom, span),
+            Expr::App(func, arg, _) => ("App", vec![func.as_ref(), arg.as_ref()]),
+            Expr::Tuple(exprs, _) => ("Tuple", exprs.iter().collect()),
+            Expr::List(exprs, _) => ("List", exprs.iter().collect()),
+            Expr::Block(exprs, _) => ("Block", exprs.iter().collect()),
+        };
+
+        writeln!(f, "{} {}", kind, expr.span())?;
+        for child in children {
+            Self::fmt_node(child, depth + 1, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Self::fmt_node(self.0, 0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::tokenize,
+        parser::{ParserConfig, parse},
+    };
+
+    /// Pretty-prints the single expression in `src`,
+    /// asserting that the output parses back into the same structure.
+    fn round_trip(src: &str) -> String {
+        let config = ParserConfig::default();
+        let exprs = parse(&tokenize(src).unwrap(), &config).unwrap();
+        let printed = exprs[0].pretty(&config.ops).to_string();
+        let reparsed = parse(&tokenize(&printed).unwrap(), &config).unwrap();
+        assert_eq!(reparsed[0].to_string(), exprs[0].to_string());
+        printed
+    }
+
+    #[test]
+    fn test_as_application() {
+        let config = ParserConfig::default();
+        let exprs = parse(&tokenize("f x y z; g; (f x) (y, z)").unwrap(), &config).unwrap();
+
+        let (head, args) = exprs[0].as_application();
+        assert_eq!(head.to_string(), "f");
+        let args: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(args, vec!["x", "y", "z"]);
+
+        let (head, args) = exprs[1].as_application();
+        assert_eq!(head.to_string(), "g");
+        assert!(args.is_empty());
+
+        let (head, args) = exprs[2].as_application();
+        assert_eq!(head.to_string(), "f");
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1].to_string(), "(y, z)");
+    }
+
+    #[test]
+    fn test_depth() {
+        let config = ParserConfig::default();
+        let exprs = parse(
+            &tokenize("x; f x y; a + b * c; [x, {y; g z}]").unwrap(),
+            &config,
+        )
+        .unwrap();
+        let depths: Vec<_> = exprs.iter().map(Expr::depth).collect();
+        assert_eq!(depths, vec![1, 3, 4, 4]);
+    }
+
+    #[test]
+    fn test_from_application() {
+        let config = ParserConfig::default();
+        let exprs = parse(&tokenize("f x y z").unwrap(), &config).unwrap();
+        let (head, args) = exprs[0].as_application();
+        let expr = Expr::from_application(head.clone(), args.into_iter().cloned());
+        assert_eq!(expr.to_string(), exprs[0].to_string());
+        assert_eq!(expr.span(), exprs[0].span());
+        assert_eq!(Expr::from_application(head.clone(), []).to_string(), "f");
+    }
+
+    #[test]
+    fn test_path_at() {
+        let config = ParserConfig::default();
+        let exprs = parse(&tokenize("f (g [1, h x]) y").unwrap(), &config).unwrap();
+        let path: Vec<_> = exprs[0]
+            .path_at(Pos(1, 12))
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect();
+        assert_eq!(
+            path,
+            vec![
+                "((f (g [1, (h x)])) y)",
+                "(f (g [1, (h x)]))",
+                "(g [1, (h x)])",
+                "[1, (h x)]",
+                "(h x)",
+                "x",
+            ]
+        );
+
+        // Between the function and the argument, only the application contains the cursor
+        let path = exprs[0].path_at(Pos(1, 2));
+        assert_eq!(path.len(), 2);
+        assert!(exprs[0].path_at(Pos(2, 1)).is_empty());
+    }
+
+    #[test]
+    fn test_tree() {
+        let config = ParserConfig::default();
+        let exprs = parse(&tokenize("f (x, [1]) + {y}").unwrap(), &config).unwrap();
+        assert_eq!(
+            exprs[0].tree().to_string(),
+            "\
+App [1:1, 1:16]
+  App [1:1, 1:12]
+    Name(\"+\") [1:12, 1:12]
+    App [1:1, 1:9]
+      Name(\"f\") [1:1, 1:1]
+      Tuple [1:4, 1:9]
+        Name(\"x\") [1:4, 1:4]
+        List [1:7, 1:9]
+          IntLit(1) [1:8, 1:8]
+  Block [1:14, 1:16]
+    Name(\"y\") [1:15, 1:15]
+"
+        );
+    }
+
+    #[test]
+    fn test_pretty_atoms() {
+        assert_eq!(round_trip("()"), "()");
+        assert_eq!(round_trip("'\\n'"), "'\\n'");
+        assert_eq!(round_trip("\"a\\tb\""), "\"a\\tb\"");
+        assert_eq!(round_trip("_"), "_");
+        assert_eq!(round_trip("(+)"), "(+)");
+    }
+
+    #[test]
+    fn test_pretty_application() {
+        assert_eq!(round_trip("(cons x xs)"), "cons x xs");
+        assert_eq!(round_trip("f (g x) (-y) (+)"), "f (g x) (-y) (+)");
+        assert_eq!(round_trip("println (!r)"), "println !r");
+        assert_eq!(round_trip("m.f (x.y)"), "m.f x.y");
+        assert_eq!(round_trip("(f x).y"), "(f x) . y");
+    }
+
+    #[test]
+    fn test_pretty_types() {
+        assert_eq!(round_trip("a -> b -> c"), "a -> b -> c");
+        assert_eq!(round_trip("(a -> b) -> c"), "(a -> b) -> c");
+        assert_eq!(round_trip("[Int]"), "[Int]");
+        assert_eq!(round_trip("x: (Int * Char)"), "x : Int * Char");
+    }
+
+    #[test]
+    fn test_pretty_patterns() {
+        assert_eq!(round_trip("(cons h _) => h"), "cons h _ => h");
+        assert_eq!(round_trip("(a, (b, c))"), "a, (b, c)");
+        assert_eq!(round_trip("((a, b), c)"), "(a, b), c");
+        assert_eq!(round_trip("[(a, b)]"), "[(a, b)]");
+    }
+
+    #[test]
+    fn test_pretty_operators() {
+        assert_eq!(round_trip("-(a + b) * c"), "-(a + b) * c");
+        assert_eq!(round_trip("a - (b - c)"), "a - (b - c)");
+        assert_eq!(round_trip("(a - b) - c"), "a - b - c");
+        assert_eq!(round_trip("- (-x)"), "-(-x)");
+        assert_eq!(round_trip("- 'a'"), "-('a')");
+        assert_eq!(round_trip("(a < b) == (c > d)"), "(a < b) == (c > d)");
+        assert_eq!(round_trip("a + b < c && d"), "a + b < c && d");
+    }
+
+    #[test]
+    fn test_pretty_block() {
+        assert_eq!(round_trip("{a = 1; a}"), "{a = 1; a}");
+    }
+}
@@ -1,18 +1,91 @@
-use std::{error, fmt};
+use std::{
+    borrow::Borrow,
+    cmp::Reverse,
+    collections::BTreeMap,
+    error,
+    fmt::{self, Write},
+};
 
-use crate::token::Span;
+use crate::token::{Pos, Span};
 
 /// Kind of an error.
 #[derive(Debug)]
 pub enum ErrorKind {
     // Lexing errors
     EmptyCharLit,
+    FloatLitTooLarge,
+    IntLitTooLarge,
+    InvalidBacktickName,
     InvalidNumLitFormat,
+    InvalidUnicodeEscape,
     MultipleCharsInCharLit,
+    TabChar,
     UnexpectedChar,
     UnknownEscapeSeq,
-    UnterminatedCharOrStrLit,
+    UnterminatedBacktickName,
+    UnterminatedCharLit,
+    UnterminatedStrLit,
     // Parsing errors
+    /// Carries the suggested replacement for the chain.
+    ChainedComparison(String),
+    /// Carries the name of the field.
+    DuplicateField(String),
+    ExpectedExpr,
+    NestingTooDeep,
+    /// Carries what would have been valid instead.
+    UnexpectedEof(Vec<&'static str>),
+    /// Carries the token found and what would have been valid instead.
+    UnexpectedToken(String, Vec<&'static str>),
+    // Evaluation errors
+    DivisionByZero,
+    EmptyRange,
+    IntOverflow,
+    InvalidMain,
+    MissingMain,
+    PatternMismatch,
+    TypeMismatch,
+    UnboundName(String),
+    UnsupportedExpr,
+    // Type errors
+    InfiniteType,
+    /// Carries the expected type and the type found.
+    MismatchedTypes(String, String),
+    // Warnings
+    /// Carries the replacement for the deprecated syntax.
+    DeprecatedSyntax(String),
+    /// Carries the constructors not covered.
+    NonExhaustivePatterns(Vec<String>),
+    RedundantSemicolon,
+    UnreachableAlt,
+}
+
+/// Severity of a diagnostic, in increasing order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl ErrorKind {
+    /// Returns the severity of diagnostics of this kind.
+    pub fn severity(&self) -> Severity {
+        match self {
+            ErrorKind::DeprecatedSyntax(_)
+            | ErrorKind::NonExhaustivePatterns(_)
+            | ErrorKind::RedundantSemicolon
+            | ErrorKind::UnreachableAlt => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
 }
 
 /// Error occurring during the compilation process.
@@ -28,23 +101,710 @@ impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ErrorKind::EmptyCharLit => write!(f, "empty character literal"),
+            ErrorKind::FloatLitTooLarge => write!(f, "float literal too large"),
+            ErrorKind::IntLitTooLarge => write!(f, "integer literal too large"),
+            ErrorKind::InvalidBacktickName => {
+                write!(
+                    f,
+                    "backtick name must be a single, possibly qualified, alphabetic name"
+                )
+            }
             ErrorKind::InvalidNumLitFormat => write!(f, "invalid number literal format"),
+            ErrorKind::InvalidUnicodeEscape => {
+                write!(f, "unicode escape is not a valid character")
+            }
             ErrorKind::MultipleCharsInCharLit => {
                 write!(f, "multiple characters in character literal")
             }
+            ErrorKind::TabChar => write!(f, "tab character"),
             ErrorKind::UnexpectedChar => write!(f, "unexpected character"),
             ErrorKind::UnknownEscapeSeq => write!(f, "unknown escape sequence"),
-            ErrorKind::UnterminatedCharOrStrLit => {
-                write!(f, "unterminated character/string literal")
+            ErrorKind::UnterminatedBacktickName => write!(f, "unterminated backtick name"),
+            ErrorKind::UnterminatedCharLit => write!(f, "unterminated character literal"),
+            ErrorKind::UnterminatedStrLit => write!(f, "unterminated string literal"),
+            ErrorKind::ChainedComparison(hint) => {
+                write!(f, "comparisons cannot be chained; write `{}` instead", hint)
+            }
+            ErrorKind::DuplicateField(name) => write!(f, "duplicate field `{}`", name),
+            ErrorKind::ExpectedExpr => write!(f, "expected expression"),
+            ErrorKind::NestingTooDeep => write!(f, "expression nested too deeply"),
+            ErrorKind::UnexpectedEof(expected) => {
+                write!(f, "unexpected end of input")?;
+                if !expected.is_empty() {
+                    write!(f, ", ")?;
+                    write_expected(f, expected)?;
+                }
+                Ok(())
+            }
+            ErrorKind::UnexpectedToken(found, expected) if expected.is_empty() => {
+                write!(f, "unexpected {}", found)
+            }
+            ErrorKind::UnexpectedToken(found, expected) => {
+                write_expected(f, expected)?;
+                write!(f, ", found {}", found)
+            }
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+            ErrorKind::EmptyRange => write!(f, "range is empty, as its start exceeds its end"),
+            ErrorKind::IntOverflow => write!(f, "integer overflow"),
+            ErrorKind::InvalidMain => write!(f, "`main` must be a function of `()`"),
+            ErrorKind::MissingMain => write!(f, "no `main` function to run"),
+            ErrorKind::PatternMismatch => write!(f, "value does not match pattern"),
+            ErrorKind::TypeMismatch => write!(f, "type mismatch"),
+            ErrorKind::UnboundName(name) => write!(f, "unbound name `{}`", name),
+            ErrorKind::UnsupportedExpr => write!(f, "unsupported expression"),
+            ErrorKind::InfiniteType => write!(f, "type would be infinite"),
+            ErrorKind::MismatchedTypes(expected, found) => {
+                write!(f, "expected type `{}`, found `{}`", expected, found)
+            }
+            ErrorKind::DeprecatedSyntax(replacement) => {
+                write!(f, "deprecated syntax; write `{}` instead", replacement)
             }
+            ErrorKind::NonExhaustivePatterns(missing) => {
+                let missing: Vec<_> = missing.iter().map(|ctor| format!("`{}`", ctor)).collect();
+                write!(f, "patterns not exhaustive; missing {}", missing.join(", "))
+            }
+            ErrorKind::RedundantSemicolon => write!(f, "redundant semicolon"),
+            ErrorKind::UnreachableAlt => write!(f, "unreachable alternative"),
         }
     }
 }
 
+/// Writes the list of what would have been valid at an error,
+/// e.g. "expected one of `)`, operator".
+fn write_expected(f: &mut fmt::Formatter<'_>, expected: &[&str]) -> fmt::Result {
+    match expected {
+        [single] => write!(f, "expected {}", single),
+        _ => write!(f, "expected one of {}", expected.join(", ")),
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Error: {} at {}", self.0, self.1)
+        write!(f, "{}: {}", self.0.severity(), self.0)?;
+        if !self.1.is_dummy() {
+            write!(f, " at {}", self.1)?;
+        }
+        Ok(())
     }
 }
 
 impl error::Error for Error {}
+
+impl Error {
+    /// Renders the error along with the line of Lynx source it occurs at.
+    pub fn report(&self, src: &str) -> String {
+        report(std::slice::from_ref(self), src)
+    }
+}
+
+/// Collector of diagnostics, i.e. errors and warnings,
+/// shared by the compilation phases.
+#[derive(Debug)]
+pub struct Diagnostics {
+    /// Diagnostics emitted so far, in emission order.
+    diags: Vec<Error>,
+
+    /// Severity below which emitted diagnostics are dropped.
+    min_severity: Severity,
+
+    /// Number of diagnostics beyond which reported ones are left out, if any.
+    max: Option<usize>,
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Diagnostics {
+    /// Creates an empty [`Diagnostics`] keeping diagnostics of all severities.
+    pub fn new() -> Self {
+        Self::with_min_severity(Severity::Warning)
+    }
+
+    /// Creates an empty [`Diagnostics`]
+    /// dropping diagnostics less severe than `min_severity`.
+    pub fn with_min_severity(min_severity: Severity) -> Self {
+        Self {
+            diags: Vec::new(),
+            min_severity,
+            max: None,
+        }
+    }
+
+    /// Caps the number of diagnostics reported
+    /// by [`Self::into_vec`] and [`Self::render_all`] at `max`,
+    /// so that the rest are only counted; see [`Self::dropped`].
+    pub fn limit(&mut self, max: usize) {
+        self.max = Some(max);
+    }
+
+    /// Records a diagnostic, unless it is not severe enough.
+    pub fn emit(&mut self, diag: Error) {
+        if diag.0.severity() >= self.min_severity {
+            self.diags.push(diag);
+        }
+    }
+
+    /// Returns the number of diagnostics left out of the report
+    /// for exceeding the cap set by [`Self::limit`].
+    pub fn dropped(&self) -> usize {
+        select(self.diags.iter().collect(), self.max).1
+    }
+
+    /// Checks if any diagnostic of [`Severity::Error`] has been emitted.
+    pub fn has_errors(&self) -> bool {
+        self.diags
+            .iter()
+            .any(|diag| diag.0.severity() == Severity::Error)
+    }
+
+    /// Checks if no diagnostic severe enough to be recorded has been emitted,
+    /// including any left out of the report by [`Self::limit`].
+    pub fn is_empty(&self) -> bool {
+        self.diags.is_empty()
+    }
+
+    /// Returns the diagnostics sorted by position,
+    /// with duplicates of the same message at the same span removed,
+    /// and capped as per [`Self::limit`].
+    pub fn into_vec(self) -> Vec<Error> {
+        select(self.diags, self.max).0
+    }
+
+    /// Renders all diagnostics for a whole run,
+    /// measuring columns per character; see [`Self::render_all_with`].
+    pub fn render_all(&self, src: &str) -> String {
+        self.render_all_with(src, ColumnMode::Chars)
+    }
+
+    /// Renders all diagnostics for a whole run like [`report_with`],
+    /// sorted, deduplicated, and capped as by [`Self::into_vec`],
+    /// followed by the number of those left out for the cap, if any.
+    pub fn render_all_with(&self, src: &str, mode: ColumnMode) -> String {
+        let (diags, dropped) = select(self.diags.iter().collect(), self.max);
+        let mut out = render(diags, src, mode);
+        if dropped > 0 {
+            writeln!(out, "... and {} more", dropped).unwrap();
+        }
+        out
+    }
+}
+
+/// Sorts diagnostics by position and removes duplicates,
+/// then keeps at most `max` of them,
+/// returning those kept along with the number of those left out.
+///
+/// Errors are kept before any warning,
+/// so that warnings never push out what fails the run.
+fn select<E: Borrow<Error>>(mut diags: Vec<E>, max: Option<usize>) -> (Vec<E>, usize) {
+    let key = |diag: &E| sort_key(diag.borrow());
+    diags.sort_by_cached_key(key);
+    diags.dedup_by(|a, b| key(a) == key(b));
+    let Some(max) = max.filter(|&max| diags.len() > max) else {
+        return (diags, 0);
+    };
+    let dropped = diags.len() - max;
+    // Stable, so that diagnostics of the same severity stay in order by position
+    diags.sort_by_key(|diag| Reverse(diag.borrow().0.severity()));
+    diags.truncate(max);
+    diags.sort_by_cached_key(key);
+    (diags, dropped)
+}
+
+/// Returns the key diagnostics are sorted and deduplicated by.
+fn sort_key(Error(kind, span): &Error) -> (Span, String) {
+    (*span, kind.to_string())
+}
+
+/// Pads `s` with spaces until it is `width` characters long.
+fn pad_to(s: &mut String, width: usize) {
+    let len = s.chars().count();
+    s.extend(std::iter::repeat_n(' ', width.saturating_sub(len)));
+}
+
+/// How columns are measured when placing carets under a line of Lynx source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnMode {
+    /// One column per character.
+    Chars,
+    /// Columns as rendered by terminals,
+    /// so that carets line up under wide characters such as CJK and emoji.
+    #[cfg(feature = "unicode-width")]
+    Display,
+}
+
+impl ColumnMode {
+    /// Returns the number of columns `s` takes up.
+    fn width(self, s: &str) -> usize {
+        match self {
+            ColumnMode::Chars => s.chars().count(),
+            #[cfg(feature = "unicode-width")]
+            ColumnMode::Display => unicode_width::UnicodeWidthStr::width(s),
+        }
+    }
+}
+
+/// Renders errors along with the lines of Lynx source they occur at,
+/// measuring columns per character; see [`report_with`].
+pub fn report(errors: &[Error], src: &str) -> String {
+    report_with(errors, src, ColumnMode::Chars)
+}
+
+/// Renders errors along with the lines of Lynx source they occur at,
+/// measuring columns as per `mode`.
+///
+/// Errors starting on the same line are grouped together,
+/// so that the line is rendered once with the carets and labels of all of them.
+/// Errors with a [`Span::dummy`] are rendered without a line.
+pub fn report_with(errors: &[Error], src: &str, mode: ColumnMode) -> String {
+    render(errors, src, mode)
+}
+
+/// Renders errors as per [`report_with`], wherever they are held.
+fn render<'e>(errors: impl IntoIterator<Item = &'e Error>, src: &str, mode: ColumnMode) -> String {
+    let mut groups: BTreeMap<usize, Vec<&Error>> = BTreeMap::new();
+    for err in errors {
+        let Span(Pos(line_no, _), _) = err.1;
+        groups.entry(line_no).or_default().push(err);
+    }
+
+    let lines: Vec<&str> = src.lines().collect();
+    let mut out = String::new();
+
+    for (line_no, mut group) in groups {
+        group.sort_by_key(|Error(_, span)| *span);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        for err in &group {
+            writeln!(out, "{}", err).unwrap();
+        }
+        // Dummy spans have no line to render
+        if line_no == 0 {
+            continue;
+        }
+
+        let line = line_no
+            .checked_sub(1)
+            .and_then(|idx| lines.get(idx))
+            .copied()
+            .unwrap_or("");
+        // Columns taken up by the line before column `col_no`,
+        // which may lie past its end
+        let offset = |col_no: usize| {
+            let prefix: String = line.chars().take(col_no - 1).collect();
+            mode.width(&prefix) + (col_no - 1).saturating_sub(prefix.chars().count())
+        };
+        let gutter = line_no.to_string();
+        let blank_gutter = " ".repeat(gutter.len());
+        writeln!(out, "{} |", blank_gutter).unwrap();
+        writeln!(out, "{} | {}", gutter, line).unwrap();
+
+        // Carets of all errors, followed by the label of the last one
+        let mut carets = String::new();
+        for Error(_, Span(Pos(_, start_col), Pos(end_line, end_col))) in &group {
+            // Spans running past the line are cut at its end
+            let end_col = if *end_line == line_no {
+                *end_col
+            } else {
+                line.chars().count()
+            };
+            pad_to(&mut carets, offset(*start_col));
+            let width = offset(end_col + 1).saturating_sub(offset(*start_col));
+            carets.push_str(&"^".repeat(width.max(1)));
+        }
+        let cols: Vec<usize> = group
+            .iter()
+            .map(|Error(_, Span(Pos(_, col_no), _))| *col_no)
+            .collect();
+        writeln!(
+            out,
+            "{} | {} {}",
+            blank_gutter,
+            carets,
+            group[group.len() - 1].0
+        )
+        .unwrap();
+
+        // Labels of the others, each hanging below its carets
+        for idx in (0..group.len() - 1).rev() {
+            let mut markers = String::new();
+            for col_no in &cols[..=idx] {
+                pad_to(&mut markers, offset(*col_no));
+                markers.push('|');
+            }
+            writeln!(out, "{} | {}", blank_gutter, markers).unwrap();
+
+            let mut label = String::new();
+            for col_no in &cols[..idx] {
+                pad_to(&mut label, offset(*col_no));
+                label.push('|');
+            }
+            pad_to(&mut label, offset(cols[idx]));
+            write!(label, "{}", group[idx].0).unwrap();
+            writeln!(out, "{} | {}", blank_gutter, label).unwrap();
+        }
+    }
+
+    out
+}
+
+/// [`Error`] along with the Lynx source it occurs in,
+/// rendered as a [`miette::Diagnostic`] with the span labeled.
+#[cfg(feature = "miette")]
+#[derive(Debug)]
+pub struct SpanError {
+    /// The error.
+    pub error: Error,
+
+    /// Lynx source the span of the error refers to.
+    pub src: String,
+}
+
+#[cfg(feature = "miette")]
+impl SpanError {
+    /// Creates [`SpanError`] from an error and the source it occurs in.
+    pub fn new(error: Error, src: impl Into<String>) -> Self {
+        Self {
+            error,
+            src: src.into(),
+        }
+    }
+}
+
+#[cfg(feature = "miette")]
+impl fmt::Display for SpanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error.0)
+    }
+}
+
+#[cfg(feature = "miette")]
+impl error::Error for SpanError {}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for SpanError {
+    /// Returns the name of the kind of the error, e.g. `lynx::ExpectedExpr`.
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let kind = format!("{:?}", self.error.0);
+        let name = kind.split('(').next().unwrap_or_default();
+        Some(Box::new(format!("lynx::{}", name)))
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(match self.error.0.severity() {
+            Severity::Warning => miette::Severity::Warning,
+            Severity::Error => miette::Severity::Error,
+        })
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.src)
+    }
+
+    /// Labels the span of the error by byte offsets, unless it is [`Span::dummy`].
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let Error(kind, span) = &self.error;
+        if span.is_dummy() {
+            return None;
+        }
+        let offset = span.0.byte_offset(&self.src);
+        let label =
+            miette::LabeledSpan::new(Some(kind.to_string()), offset, span.text(&self.src).len());
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorKind::*, *};
+
+    #[test]
+    fn test_diagnostics_has_errors() {
+        let mut diags = Diagnostics::new();
+        assert!(diags.is_empty());
+        diags.emit(Error(RedundantSemicolon, Span(Pos(1, 1), Pos(1, 1))));
+        assert!(!diags.has_errors());
+        diags.emit(Error(ExpectedExpr, Span(Pos(1, 3), Pos(1, 5))));
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_diagnostics_sorts_and_dedups() {
+        let mut diags = Diagnostics::new();
+        diags.emit(Error(ExpectedExpr, Span(Pos(2, 1), Pos(2, 1))));
+        diags.emit(Error(ExpectedExpr, Span(Pos(1, 4), Pos(1, 4))));
+        diags.emit(Error(RedundantSemicolon, Span(Pos(1, 4), Pos(1, 4))));
+        diags.emit(Error(ExpectedExpr, Span(Pos(2, 1), Pos(2, 1))));
+        let diags: Vec<_> = diags
+            .into_vec()
+            .iter()
+            .map(|Error(kind, Span(Pos(line_no, col_no), _))| {
+                format!("{}:{} {}", line_no, col_no, kind)
+            })
+            .collect();
+        assert_eq!(
+            diags,
+            vec![
+                "1:4 expected expression",
+                "1:4 redundant semicolon",
+                "2:1 expected expression"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_min_severity() {
+        let mut diags = Diagnostics::with_min_severity(Severity::Error);
+        diags.emit(Error(RedundantSemicolon, Span(Pos(1, 1), Pos(1, 1))));
+        assert!(diags.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostics_limit() {
+        let mut diags = Diagnostics::new();
+        diags.limit(2);
+        for col_no in 1..=4 {
+            diags.emit(Error(
+                RedundantSemicolon,
+                Span(Pos(1, col_no), Pos(1, col_no)),
+            ));
+        }
+        diags.emit(Error(ExpectedExpr, Span(Pos(2, 1), Pos(2, 1))));
+        assert_eq!(diags.dropped(), 3);
+        assert!(diags.has_errors());
+        // The error takes the place of a warning
+        let cols: Vec<_> = diags
+            .into_vec()
+            .iter()
+            .map(|Error(_, Span(Pos(_, col_no), _))| *col_no)
+            .collect();
+        assert_eq!(cols, vec![1, 1]);
+
+        // Dropped errors still count
+        let mut diags = Diagnostics::new();
+        diags.limit(1);
+        diags.emit(Error(ExpectedExpr, Span(Pos(1, 1), Pos(1, 1))));
+        diags.emit(Error(DivisionByZero, Span(Pos(2, 1), Pos(2, 1))));
+        assert_eq!(diags.dropped(), 1);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_render_all() {
+        let src = "f (x;\n  a;;\ny = 1 / 0";
+        let mut diags = Diagnostics::new();
+        diags.limit(3);
+        diags.emit(Error(DivisionByZero, Span(Pos(3, 5), Pos(3, 9))));
+        diags.emit(Error(RedundantSemicolon, Span(Pos(2, 4), Pos(2, 4))));
+        diags.emit(Error(ExpectedExpr, Span(Pos(1, 5), Pos(1, 5))));
+        // Duplicates are left out without taking up the cap,
+        // and diagnostics beyond the cap counted
+        diags.emit(Error(ExpectedExpr, Span(Pos(1, 5), Pos(1, 5))));
+        diags.emit(Error(RedundantSemicolon, Span(Pos(2, 5), Pos(2, 5))));
+        assert_eq!(
+            diags.render_all(src),
+            "\
+Error: expected expression at [1:5, 1:5]
+  |
+1 | f (x;
+  |     ^ expected expression
+
+Warning: redundant semicolon at [2:4, 2:4]
+  |
+2 |   a;;
+  |    ^ redundant semicolon
+
+Error: division by zero at [3:5, 3:9]
+  |
+3 | y = 1 / 0
+  |     ^^^^^ division by zero
+... and 1 more
+"
+        );
+        assert_eq!(Diagnostics::new().render_all(src), "");
+    }
+
+    #[test]
+    fn test_report_warning() {
+        let err = Error(RedundantSemicolon, Span(Pos(1, 3), Pos(1, 3)));
+        assert_eq!(
+            err.report("a;;"),
+            "\
+Warning: redundant semicolon at [1:3, 1:3]
+  |
+1 | a;;
+  |   ^ redundant semicolon
+"
+        );
+    }
+
+    #[test]
+    fn test_report_single_error() {
+        let err = Error(ExpectedExpr, Span(Pos(2, 3), Pos(2, 5)));
+        assert_eq!(
+            err.report("foo;\na +-- 1;\n"),
+            "\
+Error: expected expression at [2:3, 2:5]
+  |
+2 | a +-- 1;
+  |   ^^^ expected expression
+"
+        );
+    }
+
+    #[test]
+    fn test_report_groups_errors_on_same_line() {
+        let errors = [
+            Error(
+                UnexpectedToken("`]`".to_string(), vec![]),
+                Span(Pos(1, 11), Pos(1, 11)),
+            ),
+            Error(ExpectedExpr, Span(Pos(1, 3), Pos(1, 5))),
+        ];
+        assert_eq!(
+            report(&errors, "a +-- 123 ]"),
+            "\
+Error: expected expression at [1:3, 1:5]
+Error: unexpected `]` at [1:11, 1:11]
+  |
+1 | a +-- 123 ]
+  |   ^^^     ^ unexpected `]`
+  |   |
+  |   expected expression
+"
+        );
+    }
+
+    #[test]
+    fn test_report_separates_lines() {
+        let errors = [
+            Error(UnexpectedChar, Span(Pos(2, 1), Pos(2, 1))),
+            Error(UnexpectedChar, Span(Pos(1, 2), Pos(1, 2))),
+        ];
+        assert_eq!(
+            report(&errors, "a§\n§"),
+            "\
+Error: unexpected character at [1:2, 1:2]
+  |
+1 | a§
+  |  ^ unexpected character
+
+Error: unexpected character at [2:1, 2:1]
+  |
+2 | §
+  | ^ unexpected character
+"
+        );
+    }
+
+    #[test]
+    fn test_report_point_at_eof() {
+        let src = "f (x";
+        let tokens = crate::lexer::tokenize(src).unwrap();
+        let Err(err) = crate::parser::parse(&tokens, &Default::default()) else {
+            panic!("expected unexpected end of input");
+        };
+        assert_eq!(err.1, Span::point(Pos(1, 5)));
+        assert_eq!(
+            err.report(src),
+            "\
+Error: unexpected end of input, expected one of `)`, argument, operator at [1:5, 1:5]
+  |
+1 | f (x
+  |     ^ unexpected end of input, expected one of `)`, argument, operator
+"
+        );
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_span_error() {
+        use miette::Diagnostic;
+
+        let src = "x = 1;\ny = \"λ\" +;";
+        let err = SpanError::new(Error(ExpectedExpr, Span(Pos(2, 5), Pos(2, 7))), src);
+        assert_eq!(err.to_string(), "expected expression");
+        assert_eq!(err.code().unwrap().to_string(), "lynx::ExpectedExpr");
+        assert!(matches!(err.severity(), Some(miette::Severity::Error)));
+
+        let labels: Vec<_> = err.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!((labels[0].offset(), labels[0].len()), (11, 4));
+        assert_eq!(labels[0].label(), Some("expected expression"));
+
+        let err = SpanError::new(Error(UnreachableAlt, Span::dummy()), src);
+        assert!(err.labels().is_none());
+        assert!(matches!(err.severity(), Some(miette::Severity::Warning)));
+    }
+
+    #[test]
+    fn test_report_wide_chars() {
+        let errors = [
+            Error(ExpectedExpr, Span(Pos(1, 3), Pos(1, 4))),
+            Error(RedundantSemicolon, Span(Pos(1, 8), Pos(1, 8))),
+        ];
+        let src = "x 漢字 = ;;";
+        assert_eq!(
+            report_with(&errors, src, ColumnMode::Chars),
+            "\
+Error: expected expression at [1:3, 1:4]
+Warning: redundant semicolon at [1:8, 1:8]
+  |
+1 | x 漢字 = ;;
+  |   ^^   ^ redundant semicolon
+  |   |
+  |   expected expression
+"
+        );
+
+        #[cfg(feature = "unicode-width")]
+        assert_eq!(
+            report_with(&errors, src, ColumnMode::Display),
+            "\
+Error: expected expression at [1:3, 1:4]
+Warning: redundant semicolon at [1:8, 1:8]
+  |
+1 | x 漢字 = ;;
+  |   ^^^^   ^ redundant semicolon
+  |   |
+  |   expected expression
+"
+        );
+    }
+
+    #[test]
+    fn test_report_dummy_span() {
+        let errors = [
+            Error(ExpectedExpr, Span(Pos(1, 1), Pos(1, 1))),
+            Error(UnreachableAlt, Span::dummy()),
+        ];
+        assert_eq!(
+            report(&errors, "x"),
+            "\
+Warning: unreachable alternative
+
+Error: expected expression at [1:1, 1:1]
+  |
+1 | x
+  | ^ expected expression
+"
+        );
+    }
+
+    #[test]
+    fn test_report_cuts_multiline_span() {
+        let err = Error(UnexpectedEof(vec![]), Span(Pos(1, 3), Pos(2, 1)));
+        assert_eq!(
+            err.report("a (b\nc"),
+            "\
+Error: unexpected end of input at [1:3, 2:1]
+  |
+1 | a (b
+  |   ^^ unexpected end of input
+"
+        );
+    }
+}
@@ -0,0 +1,896 @@
+use std::{cmp::Ordering, collections::HashMap, fmt, mem, rc::Rc};
+
+use crate::{
+    ast::{AtomKind, Expr},
+    error::{Error, ErrorKind::*},
+    token::Span,
+};
+
+/// Built-in binary operators understood by the evaluator.
+const BINARY_OPS: &[&str] = &[
+    "+", "-", "*", "/", "%", "++", "==", "!=", "<", ">", "<=", ">=", "&&", "||",
+];
+
+/// Runtime value of a Lynx expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Char(char),
+    Str(String),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Func(Rc<Closure>),
+}
+
+/// Function created by `=>`, along with the bindings it captures.
+#[derive(Debug)]
+pub struct Closure {
+    /// Pattern the argument is bound to.
+    param: Expr,
+
+    /// Expression evaluated when the function is applied.
+    body: Expr,
+
+    /// Bindings visible where the function was created.
+    env: HashMap<String, Value>,
+}
+
+/// Functions are equal only if they are the same one.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Char(value) => write!(f, "{:?}", value),
+            Value::Str(value) => write!(f, "{:?}", value),
+            Value::Tuple(values) => {
+                write!(f, "(")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, ")")
+            }
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Func(_) => write!(f, "<function>"),
+        }
+    }
+}
+
+/// Splits the left-hand side of a `match` alternative
+/// into the pattern and the guard, if any, as in `pat if (cond)`.
+pub(crate) fn split_guard(lhs: &Expr) -> (&Expr, Option<&Expr>) {
+    if let Expr::App(func, cond, _) = lhs
+        && let Expr::App(pattern, kw, _) = func.as_ref()
+        && let Expr::Atom(AtomKind::Name(kw), _) = kw.as_ref()
+        && kw == "if"
+    {
+        return (pattern, Some(cond));
+    }
+    (lhs, None)
+}
+
+/// Compares two values of the same ordered type,
+/// returning [`None`] for values of other types.
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Returns the value of a bound of a range pattern,
+/// i.e. a number or character literal, possibly negated,
+/// or [`None`] for any other pattern.
+fn range_bound(bound: &Expr) -> Option<Value> {
+    match bound {
+        Expr::Atom(AtomKind::IntLit(value), _) => Some(Value::Int(*value)),
+        Expr::Atom(AtomKind::FloatLit(value), _) => Some(Value::Float(*value)),
+        Expr::Atom(AtomKind::CharLit(value), _) => Some(Value::Char(*value)),
+        Expr::App(func, operand, _) => match (&**func, range_bound(operand)?) {
+            (Expr::Atom(AtomKind::Name(op), _), Value::Int(value)) if op == "negate" => {
+                Some(Value::Int(-value))
+            }
+            (Expr::Atom(AtomKind::Name(op), _), Value::Float(value)) if op == "negate" => {
+                Some(Value::Float(-value))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Tree-walking evaluator for a subset of Lynx,
+/// namely literals, tuples, lists, built-in operators,
+/// the `if`, `do`, and `match` macros, bindings, and functions.
+///
+/// This type is an internal helper for [`eval`]
+/// and is *not* intended for public use.
+struct Evaluator {
+    /// Stack of scopes, innermost last.
+    scopes: Vec<HashMap<String, Value>>,
+}
+
+impl Evaluator {
+    /// Creates [`Evaluator`] with an empty top-level scope.
+    fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    /// Looks up a name from the innermost scope outwards.
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Evaluates a sequence of expressions in the current scope,
+    /// returning the value of the last one, or `()` if there is none.
+    fn eval_seq(&mut self, exprs: &[Expr]) -> Result<Value, Error> {
+        let mut value = Value::Unit;
+        for expr in exprs {
+            value = self.eval(expr)?;
+        }
+        Ok(value)
+    }
+
+    /// Collects all bindings visible in the current scope,
+    /// to be captured by a function.
+    fn capture(&self) -> HashMap<String, Value> {
+        let mut env = HashMap::new();
+        for scope in &self.scopes {
+            env.extend(
+                scope
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+        }
+        env
+    }
+
+    /// Evaluates a block in a new scope.
+    fn eval_block(&mut self, exprs: &[Expr]) -> Result<Value, Error> {
+        self.scopes.push(HashMap::new());
+        let value = self.eval_seq(exprs);
+        self.scopes.pop();
+        value
+    }
+
+    /// Evaluates an expression.
+    fn eval(&mut self, expr: &Expr) -> Result<Value, Error> {
+        match expr {
+            Expr::Atom(atom, span) => self.eval_atom(atom, *span),
+            Expr::App(_, _, span) => self.eval_app(expr, *span),
+            Expr::Tuple(exprs, _) => Ok(Value::Tuple(
+                exprs
+                    .iter()
+                    .map(|expr| self.eval(expr))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Expr::List(exprs, _) => Ok(Value::List(
+                exprs
+                    .iter()
+                    .map(|expr| self.eval(expr))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Expr::Block(_, span) => Err(Error(UnsupportedExpr, *span)),
+        }
+    }
+
+    /// Evaluates an atom.
+    fn eval_atom(&self, atom: &AtomKind, span: Span) -> Result<Value, Error> {
+        match atom {
+            AtomKind::UnitLit => Ok(Value::Unit),
+            AtomKind::IntLit(value) => Ok(Value::Int(*value)),
+            AtomKind::FloatLit(value) => Ok(Value::Float(*value)),
+            AtomKind::CharLit(value) => Ok(Value::Char(*value)),
+            AtomKind::StrLit(value) => Ok(Value::Str(value.clone())),
+            AtomKind::Wildcard => Err(Error(UnsupportedExpr, span)),
+            AtomKind::Name(name) => match (self.lookup(name), name.as_str()) {
+                (Some(value), _) => Ok(value.clone()),
+                (None, "true") => Ok(Value::Bool(true)),
+                (None, "false") => Ok(Value::Bool(false)),
+                (None, _) => Err(Error(UnboundName(name.clone()), span)),
+            },
+        }
+    }
+
+    /// Evaluates a function application,
+    /// which may be a binding, a supported macro, a built-in operator,
+    /// or a call to a function value.
+    fn eval_app(&mut self, expr: &Expr, span: Span) -> Result<Value, Error> {
+        let (head, args) = expr.as_application();
+        let Expr::Atom(AtomKind::Name(name), _) = head else {
+            let func = self.eval(head)?;
+            return self.eval_call(func, &args, span);
+        };
+
+        match (name.as_str(), args.as_slice()) {
+            ("=", [pattern, value]) => {
+                let value = self.eval(value)?;
+                self.bind(pattern, value)?;
+                Ok(Value::Unit)
+            }
+            // The function may be applied right away, e.g. `(x => x) 1`
+            ("=>", [param, body, args @ ..]) => {
+                let func = Value::Func(Rc::new(Closure {
+                    param: (*param).clone(),
+                    body: (*body).clone(),
+                    env: self.capture(),
+                }));
+                self.eval_call(func, args, span)
+            }
+            ("if", args) => self.eval_if(args, span),
+            ("do", [Expr::Block(exprs, _)]) => self.eval_block(exprs),
+            ("match", [scrutinee, Expr::Block(alts, _)]) => self.eval_match(scrutinee, alts, span),
+            ("negate", [operand]) => match self.eval(operand)? {
+                Value::Int(value) => value
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or(Error(IntOverflow, span)),
+                Value::Float(value) => Ok(Value::Float(-value)),
+                _ => Err(Error(TypeMismatch, span)),
+            },
+            (op, [lhs, rhs]) if BINARY_OPS.contains(&op) => self.eval_binary(op, lhs, rhs, span),
+            _ => {
+                let func = self.eval(head)?;
+                self.eval_call(func, &args, span)
+            }
+        }
+    }
+
+    /// Evaluates a call to a function value, one argument at a time.
+    fn eval_call(&mut self, mut func: Value, args: &[&Expr], span: Span) -> Result<Value, Error> {
+        for arg in args {
+            let Value::Func(closure) = func else {
+                return Err(Error(TypeMismatch, span));
+            };
+            let arg = self.eval(arg)?;
+            func = self.apply(&closure, arg)?;
+        }
+        Ok(func)
+    }
+
+    /// Applies a function to an argument,
+    /// evaluating its body with only the captured bindings and the parameter in scope.
+    fn apply(&mut self, closure: &Closure, arg: Value) -> Result<Value, Error> {
+        let caller_scopes = mem::replace(&mut self.scopes, vec![closure.env.clone()]);
+        let value = self
+            .bind(&closure.param, arg)
+            .and_then(|()| self.eval(&closure.body));
+        self.scopes = caller_scopes;
+        value
+    }
+
+    /// Evaluates a condition, which must be a boolean.
+    fn eval_cond(&mut self, cond: &Expr) -> Result<bool, Error> {
+        match self.eval(cond)? {
+            Value::Bool(value) => Ok(value),
+            _ => Err(Error(TypeMismatch, cond.span())),
+        }
+    }
+
+    /// Evaluates the arguments of the `if` macro,
+    /// i.e. `cond {...} [elif cond {...}]* [else {...}]`.
+    fn eval_if(&mut self, args: &[&Expr], span: Span) -> Result<Value, Error> {
+        let mut args = args;
+
+        loop {
+            let [cond, Expr::Block(then_exprs, _), rest @ ..] = args else {
+                return Err(Error(UnsupportedExpr, span));
+            };
+            if self.eval_cond(cond)? {
+                return self.eval_block(then_exprs);
+            }
+
+            match rest {
+                [] => return Ok(Value::Unit),
+                [
+                    Expr::Atom(AtomKind::Name(kw), _),
+                    Expr::Block(else_exprs, _),
+                ] if kw == "else" => {
+                    return self.eval_block(else_exprs);
+                }
+                [Expr::Atom(AtomKind::Name(kw), _), rest @ ..] if kw == "elif" => {
+                    args = rest;
+                }
+                _ => return Err(Error(UnsupportedExpr, span)),
+            }
+        }
+    }
+
+    /// Evaluates the arguments of the `match` macro,
+    /// i.e. a scrutinee and a block of alternatives `pat [if (cond)] => expr`,
+    /// returning the value of the first alternative that matches.
+    fn eval_match(&mut self, scrutinee: &Expr, alts: &[Expr], span: Span) -> Result<Value, Error> {
+        let value = self.eval(scrutinee)?;
+
+        for alt in alts {
+            let (head, args) = alt.as_application();
+            let (Expr::Atom(AtomKind::Name(op), _), [lhs, body]) = (head, args.as_slice()) else {
+                return Err(Error(UnsupportedExpr, alt.span()));
+            };
+            if op != "=>" {
+                return Err(Error(UnsupportedExpr, alt.span()));
+            }
+
+            let (pattern, guard) = split_guard(lhs);
+            let mut bindings = HashMap::new();
+            if !self.match_pattern(pattern, &value, &mut bindings)? {
+                continue;
+            }
+
+            self.scopes.push(bindings);
+            let result = match guard {
+                Some(guard) => self.eval_cond(guard).and_then(|holds| {
+                    // `None` for a guard that does not hold
+                    holds.then(|| self.eval(body)).transpose()
+                }),
+                None => self.eval(body).map(Some),
+            };
+            self.scopes.pop();
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+
+        Err(Error(PatternMismatch, span))
+    }
+
+    /// Evaluates a built-in binary operator.
+    fn eval_binary(
+        &mut self,
+        op: &str,
+        lhs: &Expr,
+        rhs: &Expr,
+        span: Span,
+    ) -> Result<Value, Error> {
+        // Short-circuiting operators
+        if op == "&&" || op == "||" {
+            let lhs = self.eval_cond(lhs)?;
+            if lhs == (op == "||") {
+                return Ok(Value::Bool(lhs));
+            }
+            return self.eval_cond(rhs).map(Value::Bool);
+        }
+
+        let lhs = self.eval(lhs)?;
+        let rhs = self.eval(rhs)?;
+        let overflow = Error(IntOverflow, span);
+
+        match (op, lhs, rhs) {
+            ("/" | "%", Value::Int(_), Value::Int(0)) => Err(Error(DivisionByZero, span)),
+            ("+", Value::Int(a), Value::Int(b)) => a.checked_add(b).map(Value::Int).ok_or(overflow),
+            ("-", Value::Int(a), Value::Int(b)) => a.checked_sub(b).map(Value::Int).ok_or(overflow),
+            ("*", Value::Int(a), Value::Int(b)) => a.checked_mul(b).map(Value::Int).ok_or(overflow),
+            ("/", Value::Int(a), Value::Int(b)) => a.checked_div(b).map(Value::Int).ok_or(overflow),
+            ("%", Value::Int(a), Value::Int(b)) => a.checked_rem(b).map(Value::Int).ok_or(overflow),
+            ("+", Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+            ("-", Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
+            ("*", Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
+            ("/", Value::Float(a), Value::Float(b)) => Ok(Value::Float(a / b)),
+            ("%", Value::Float(a), Value::Float(b)) => Ok(Value::Float(a % b)),
+            ("++", Value::Str(a), Value::Str(b)) => Ok(Value::Str(a + &b)),
+            ("++", Value::List(mut a), Value::List(b)) => {
+                a.extend(b);
+                Ok(Value::List(a))
+            }
+            ("==" | "!=", lhs, rhs) if mem::discriminant(&lhs) == mem::discriminant(&rhs) => {
+                Ok(Value::Bool((lhs == rhs) == (op == "==")))
+            }
+            ("<" | ">" | "<=" | ">=", lhs, rhs) => {
+                let ordering = compare(&lhs, &rhs).ok_or(Error(TypeMismatch, span))?;
+                Ok(Value::Bool(match op {
+                    "<" => ordering.is_lt(),
+                    ">" => ordering.is_gt(),
+                    "<=" => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                }))
+            }
+            _ => Err(Error(TypeMismatch, span)),
+        }
+    }
+
+    /// Binds a value to a pattern in the current scope,
+    /// failing if the value does not match.
+    fn bind(&mut self, pattern: &Expr, value: Value) -> Result<(), Error> {
+        let mut bindings = HashMap::new();
+        if !self.match_pattern(pattern, &value, &mut bindings)? {
+            return Err(Error(PatternMismatch, pattern.span()));
+        }
+        self.scopes
+            .last_mut()
+            .expect("there is always a top-level scope")
+            .extend(bindings);
+        Ok(())
+    }
+
+    /// Matches a value against a pattern,
+    /// collecting the names it binds into `bindings`.
+    ///
+    /// Supported patterns are names, the wildcard, literals including `true` and `false`,
+    /// negated number literals such as `-5`, inclusive ranges such as `-3..3`, tuples, lists, alternatives separated by `|`,
+    /// views `f -> pat` matching `pat` against `f` applied to the value,
+    /// and patterns with type annotations, which are ignored.
+    fn match_pattern(
+        &mut self,
+        pattern: &Expr,
+        value: &Value,
+        bindings: &mut HashMap<String, Value>,
+    ) -> Result<bool, Error> {
+        match pattern {
+            Expr::Atom(AtomKind::Wildcard, _) => Ok(true),
+            Expr::Atom(AtomKind::Name(name), _) if name == "true" || name == "false" => {
+                Ok(*value == Value::Bool(name == "true"))
+            }
+            Expr::Atom(AtomKind::Name(name), _) => {
+                bindings.insert(name.clone(), value.clone());
+                Ok(true)
+            }
+            Expr::Atom(atom, span) => Ok(self.eval_atom(atom, *span)? == *value),
+            Expr::Tuple(patterns, _) => match value {
+                Value::Tuple(values) => self.match_all(patterns, values, bindings),
+                _ => Ok(false),
+            },
+            Expr::List(patterns, _) => match value {
+                Value::List(values) => self.match_all(patterns, values, bindings),
+                _ => Ok(false),
+            },
+            Expr::App(..) => {
+                let (head, args) = pattern.as_application();
+                match (head, args.as_slice()) {
+                    (Expr::Atom(AtomKind::Name(op), _), [pattern, _]) if op == ":" => {
+                        self.match_pattern(pattern, value, bindings)
+                    }
+                    (Expr::Atom(AtomKind::Name(op), _), [lhs, rhs]) if op == "|" => {
+                        // Bindings of a failed alternative must not leak
+                        let mut lhs_bindings = HashMap::new();
+                        if self.match_pattern(lhs, value, &mut lhs_bindings)? {
+                            bindings.extend(lhs_bindings);
+                            return Ok(true);
+                        }
+                        self.match_pattern(rhs, value, bindings)
+                    }
+                    // `-5` is `negate 5`, folded into the literal `-5`
+                    (
+                        Expr::Atom(AtomKind::Name(op), _),
+                        [Expr::Atom(AtomKind::IntLit(literal), _)],
+                    ) if op == "negate" => Ok(*value == Value::Int(-literal)),
+                    (
+                        Expr::Atom(AtomKind::Name(op), _),
+                        [Expr::Atom(AtomKind::FloatLit(literal), _)],
+                    ) if op == "negate" => Ok(*value == Value::Float(-literal)),
+                    (Expr::Atom(AtomKind::Name(op), _), [lo, hi]) if op == ".." => {
+                        let (Some(lo), Some(hi)) = (range_bound(lo), range_bound(hi)) else {
+                            return Err(Error(UnsupportedExpr, pattern.span()));
+                        };
+                        match compare(&lo, &hi) {
+                            Some(Ordering::Greater) => Err(Error(EmptyRange, pattern.span())),
+                            Some(_) => Ok(compare(value, &lo).is_some_and(Ordering::is_ge)
+                                && compare(value, &hi).is_some_and(Ordering::is_le)),
+                            None => Err(Error(TypeMismatch, pattern.span())),
+                        }
+                    }
+                    (Expr::Atom(AtomKind::Name(op), _), [view, pattern]) if op == "->" => {
+                        let Value::Func(closure) = self.eval(view)? else {
+                            return Err(Error(TypeMismatch, view.span()));
+                        };
+                        let value = self.apply(&closure, value.clone())?;
+                        self.match_pattern(pattern, &value, bindings)
+                    }
+                    _ => Err(Error(UnsupportedExpr, pattern.span())),
+                }
+            }
+            Expr::Block(..) => Err(Error(UnsupportedExpr, pattern.span())),
+        }
+    }
+
+    /// Matches values against patterns pairwise,
+    /// failing if their numbers differ.
+    fn match_all(
+        &mut self,
+        patterns: &[Expr],
+        values: &[Value],
+        bindings: &mut HashMap<String, Value>,
+    ) -> Result<bool, Error> {
+        if patterns.len() != values.len() {
+            return Ok(false);
+        }
+        for (pattern, value) in patterns.iter().zip(values) {
+            if !self.match_pattern(pattern, value, bindings)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Evaluates top-level Lynx expressions in order,
+/// returning either the value of the last one
+/// or the first [`Error`] encountered.
+pub fn eval(exprs: &[Expr]) -> Result<Value, Error> {
+    Evaluator::new().eval_seq(exprs)
+}
+
+/// Evaluates top-level Lynx expressions in order,
+/// then runs the entry point, i.e. the function `main`, with `()`,
+/// returning its result or the first [`Error`] encountered.
+pub fn run_main(exprs: &[Expr]) -> Result<Value, Error> {
+    let mut evaluator = Evaluator::new();
+    evaluator.eval_seq(exprs)?;
+
+    // Point at the last top-level definition of `main`, if any
+    let span = exprs
+        .iter()
+        .rev()
+        .find_map(|expr| {
+            let (head, args) = expr.as_application();
+            match (head, args.as_slice()) {
+                (
+                    Expr::Atom(AtomKind::Name(op), _),
+                    [Expr::Atom(AtomKind::Name(name), span), _],
+                ) if op == "=" && name == "main" => Some(*span),
+                _ => None,
+            }
+        })
+        .unwrap_or_else(Span::dummy);
+    let Some(main) = evaluator.lookup("main") else {
+        return Err(Error(MissingMain, span));
+    };
+    let Value::Func(closure) = main.clone() else {
+        return Err(Error(InvalidMain, span));
+    };
+    match evaluator.apply(&closure, Value::Unit)? {
+        // `main` taking more than one argument
+        Value::Func(_) => Err(Error(InvalidMain, span)),
+        value => Ok(value),
+    }
+}
+
+/// Evaluates top-level Lynx expressions in order,
+/// passing the value of each one to `f`,
+/// until the first [`Error`] encountered.
+pub fn eval_each(exprs: &[Expr], mut f: impl FnMut(Value)) -> Result<(), Error> {
+    let mut evaluator = Evaluator::new();
+    for expr in exprs {
+        f(evaluator.eval(expr)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::tokenize,
+        parser::{ParserConfig, parse},
+        token::Pos,
+    };
+
+    fn eval_str(src: &str) -> Result<Value, Error> {
+        let exprs = parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        eval(&exprs)
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(eval_str("()").unwrap(), Value::Unit);
+        assert_eq!(eval_str("'a'").unwrap(), Value::Char('a'));
+        assert_eq!(eval_str("1.5").unwrap(), Value::Float(1.5));
+        assert_eq!(eval_str("\"s\"").unwrap(), Value::Str("s".to_string()));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval_str("1 + 2 * 3").unwrap(), Value::Int(7));
+        assert_eq!(eval_str("(1 + 2) * 3").unwrap(), Value::Int(9));
+        assert_eq!(eval_str("7 / 2 - -1").unwrap(), Value::Int(4));
+        assert_eq!(eval_str("1.5 * 2.0").unwrap(), Value::Float(3.0));
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(eval_str("1 + 1 == 2").unwrap(), Value::Bool(true));
+        assert_eq!(
+            eval_str("'a' < 'b' && \"b\" <= \"a\"").unwrap(),
+            Value::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_if() {
+        assert_eq!(eval_str("if (true) {1} else {2}").unwrap(), Value::Int(1));
+        assert_eq!(
+            eval_str("if (1 > 2) {1} elif (false) {2} else {3}").unwrap(),
+            Value::Int(3)
+        );
+        assert_eq!(eval_str("if (false) {1}").unwrap(), Value::Unit);
+
+        let src = "f = x => x; f (if (true) {1} else {2})";
+        assert_eq!(eval_str(src).unwrap(), Value::Int(1));
+        let result = eval_str("f = x => x; f if (true) {1} else {2}");
+        assert!(matches!(result, Err(Error(UnboundName(name), _)) if name == "if"));
+    }
+
+    #[test]
+    fn test_tuple_section() {
+        assert_eq!(eval_str("(, 2) 1").unwrap(), eval_str("(1, 2)").unwrap());
+        assert_eq!(eval_str("(1 ,) 2").unwrap(), eval_str("(1, 2)").unwrap());
+        assert_eq!(
+            eval_str("(1, , 3) 2").unwrap(),
+            eval_str("(x => (1, x, 3)) 2").unwrap()
+        );
+        assert_eq!(
+            eval_str("(, 2,) 1 3").unwrap(),
+            eval_str("(1, 2, 3)").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bindings() {
+        assert_eq!(eval_str("x = 2; y = x * 3; y + 1").unwrap(), Value::Int(7));
+        assert_eq!(
+            eval_str("(a, _, b: Int) = (1, 2, 3); a - b").unwrap(),
+            Value::Int(-2)
+        );
+    }
+
+    #[test]
+    fn test_refutable_bindings() {
+        assert_eq!(eval_str("[a, _] = [1, 2]; a").unwrap(), Value::Int(1));
+        assert_eq!(eval_str("(1, x) = (1, 'c'); x").unwrap(), Value::Char('c'));
+        let result = eval_str("(0, x) = (1, 'c')");
+        assert!(matches!(result, Err(Error(PatternMismatch, _))));
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(
+            eval_str("match 2 {1 | 2 => \"small\"; _ => \"big\"}").unwrap(),
+            Value::Str("small".to_string())
+        );
+        assert_eq!(
+            eval_str("match (1 > 2) {true => 1; false => 0}").unwrap(),
+            Value::Int(0)
+        );
+        assert_eq!(
+            eval_str("match [1, 2] {[] => 0; [x] => x; [x, y] => x + y}").unwrap(),
+            Value::Int(3)
+        );
+    }
+
+    #[test]
+    fn test_range_patterns() {
+        let classify = "f = n => match n {-3..3 => \"small\"; 4..5 => \"medium\"; _ => \"large\"}";
+        for (n, size) in [
+            (-3, "small"),
+            (3, "small"),
+            (4, "medium"),
+            (5, "medium"),
+            (6, "large"),
+        ] {
+            let src = format!("{}; f ({})", classify, n);
+            assert_eq!(eval_str(&src).unwrap(), Value::Str(size.to_string()));
+        }
+        assert_eq!(
+            eval_str("match 'q' {'a' .. 'z' => 1; _ => 0}").unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(
+            eval_str("match 0.5 {0.0..1.0 => 1; _ => 0}").unwrap(),
+            Value::Int(1)
+        );
+
+        let result = eval_str("match 3 {5..1 => 1; _ => 0}");
+        let Err(Error(EmptyRange, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected empty range");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 10, 1, 13));
+        assert!(matches!(
+            eval_str("match 3 {1 .. 'a' => 1}"),
+            Err(Error(TypeMismatch, _))
+        ));
+    }
+
+    #[test]
+    fn test_negative_literals() {
+        assert_eq!(eval_str("-5").unwrap(), Value::Int(-5));
+        assert_eq!(eval_str("negate 5").unwrap(), eval_str("-5").unwrap());
+        assert_eq!(
+            eval_str("match (2 - 7) {5 => \"pos\"; -5 => \"neg\"; _ => \"other\"}").unwrap(),
+            Value::Str("neg".to_string())
+        );
+        assert_eq!(
+            eval_str("match (-1.5) {negate 1.5 => 1; _ => 0}").unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(eval_str("(-1, x) = (-1, 2); x").unwrap(), Value::Int(2));
+        assert!(matches!(eval_str("-1 = 1"), Err(Error(PatternMismatch, _))));
+    }
+
+    #[test]
+    fn test_match_guards() {
+        let src = "f = n => match n {x if (x < 3) => \"small\"; 3 => \"three\"; _ => \"big\"}";
+        assert_eq!(
+            eval_str(&format!("{}; f 1, f 3, f 5", src)).unwrap(),
+            Value::Tuple(vec![
+                Value::Str("small".to_string()),
+                Value::Str("three".to_string()),
+                Value::Str("big".to_string())
+            ])
+        );
+        assert_eq!(
+            eval_str("match (1, 2) {(a, b) if (a > b) => a; (a, b) => b}").unwrap(),
+            Value::Int(2)
+        );
+        assert_eq!(
+            eval_str("match 2 {(1 | 2) if (false) => 0; x => x}").unwrap(),
+            Value::Int(2)
+        );
+    }
+
+    #[test]
+    fn test_view_patterns() {
+        let src = "fst = (a, _) => a; match (1, 2) { (fst -> 1) => \"one\"; _ => \"other\"; }";
+        assert_eq!(eval_str(src).unwrap(), Value::Str("one".to_string()));
+        let src = "neg = x => -x; (neg -> y) = 3; y";
+        assert_eq!(eval_str(src).unwrap(), Value::Int(-3));
+        // Views bind tighter than `,`, so tuples need no extra parentheses
+        let src = "neg = x => -x; f = (a, neg -> b) => a + b; f (5, 2)";
+        assert_eq!(eval_str(src).unwrap(), Value::Int(3));
+
+        let result = eval_str("(1 -> x) = 2");
+        assert!(matches!(result, Err(Error(TypeMismatch, _))));
+    }
+
+    #[test]
+    fn test_match_errors() {
+        let result = eval_str("match 3 {1 => 1; 2 => 2}");
+        assert!(matches!(result, Err(Error(PatternMismatch, _))));
+        let result = eval_str("match 1 {x if (x) => 1}");
+        assert!(matches!(result, Err(Error(TypeMismatch, _))));
+    }
+
+    #[test]
+    fn test_block_scope() {
+        assert_eq!(
+            eval_str("x = 1; y = do {x = 2; x + 1}; x + y").unwrap(),
+            Value::Int(4)
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(
+            eval_str("add = x => y => x + y; add 1 2").unwrap(),
+            Value::Int(3)
+        );
+        assert_eq!(
+            eval_str("n = 10; f = x => x + n; n = 20; f 1").unwrap(),
+            Value::Int(11)
+        );
+        assert_eq!(eval_str("(x => x * 2) 4").unwrap(), Value::Int(8));
+    }
+
+    #[test]
+    fn test_function_param_patterns() {
+        assert_eq!(
+            eval_str("get_x = (x, _) => x; get_x (1, 2)").unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(
+            eval_str("f = ((a, b), c: Int) => a + b * c; f ((1, 2), 3)").unwrap(),
+            Value::Int(7)
+        );
+        let result = eval_str("f = (a, b) => a; f 1");
+        assert!(matches!(result, Err(Error(PatternMismatch, _))));
+    }
+
+    #[test]
+    fn test_malformed_param_error() {
+        let result = eval_str("f = (x + 1) => x; f 1");
+        let Err(Error(UnsupportedExpr, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected unsupported expression");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 6, 1, 10));
+    }
+
+    #[test]
+    fn test_call_non_function_error() {
+        let result = eval_str("x = 1; x 2");
+        assert!(matches!(result, Err(Error(TypeMismatch, _))));
+    }
+
+    #[test]
+    fn test_collections() {
+        assert_eq!(
+            eval_str("[1] ++ [2], 'c'").unwrap(),
+            Value::Tuple(vec![
+                Value::List(vec![Value::Int(1), Value::Int(2)]),
+                Value::Char('c')
+            ])
+        );
+    }
+
+    #[test]
+    fn test_eval_each() {
+        let exprs = parse(
+            &tokenize("x = 2\n\nx * 3; x + 1").unwrap(),
+            &ParserConfig::default(),
+        )
+        .unwrap();
+        let mut values = Vec::new();
+        eval_each(&exprs, |value| values.push(value)).unwrap();
+        assert_eq!(values, vec![Value::Unit, Value::Int(6), Value::Int(3)]);
+    }
+
+    #[test]
+    fn test_run_main() {
+        let run_str =
+            |src: &str| run_main(&parse(&tokenize(src).unwrap(), &Default::default()).unwrap());
+        assert_eq!(
+            run_str("x = 20; main = () => x * 2 + 2").unwrap(),
+            Value::Int(42)
+        );
+
+        assert!(matches!(run_str("x = 1"), Err(Error(MissingMain, span)) if span.is_dummy()));
+        let result = run_str("main = 1");
+        assert!(matches!(
+            result,
+            Err(Error(InvalidMain, Span(Pos(1, 1), Pos(1, 4))))
+        ));
+        let result = run_str("main = () => x => x");
+        assert!(matches!(result, Err(Error(InvalidMain, _))));
+    }
+
+    #[test]
+    fn test_unbound_name_error() {
+        let result = eval_str("x = 1; x + y");
+        assert!(matches!(result, Err(Error(UnboundName(name), _)) if name == "y"));
+    }
+
+    #[test]
+    fn test_type_mismatch_error() {
+        let result = eval_str("1 + true");
+        assert!(matches!(result, Err(Error(TypeMismatch, _))));
+        let result = eval_str("if (1) {2}");
+        assert!(matches!(result, Err(Error(TypeMismatch, _))));
+    }
+
+    #[test]
+    fn test_pattern_mismatch_error() {
+        let result = eval_str("(a, b) = (1, 2, 3)");
+        assert!(matches!(result, Err(Error(PatternMismatch, _))));
+    }
+
+    #[test]
+    fn test_division_by_zero_error() {
+        let result = eval_str("1 / 0");
+        assert!(matches!(result, Err(Error(DivisionByZero, _))));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::Error,
+    lexer::{SYM_CHARS, tokenize_with_trivia},
+    parser::{APP_PREC, Fixity, OpTable, ParserConfig, is_sym_name, parse},
+    token::{Comment, Token, TokenKind::*},
+};
+
+/// One level of indentation.
+const INDENT: &str = "    ";
+
+/// Checks if a token ends an item rather than leaving an expression open,
+/// so that the next line starts afresh instead of continuing it.
+fn ends_item(token: &Token) -> bool {
+    match &token.0 {
+        Semicolon | Lp | Lb | Lc => true,
+        Name(name) => name == ",",
+        _ => false,
+    }
+}
+
+/// Checks if a token leaves the parser expecting an operand,
+/// so that a prefix operator after it is in prefix position.
+fn expects_operand(token: &Token) -> bool {
+    match &token.0 {
+        Lp | Lb | Lc | Semicolon | BlankLine | BacktickName(_) => true,
+        Name(name) => is_sym_name(name),
+        _ => false,
+    }
+}
+
+/// Checks if `name` is an infix operator binding tighter than application,
+/// e.g. `.`, which is written without spaces around.
+fn is_access(name: &str, ops: &OpTable) -> bool {
+    ops.infix(name)
+        .is_some_and(|Fixity(_, prec)| prec > APP_PREC)
+}
+
+/// Checks if two tokens would be lexed as one if written without a space between,
+/// e.g. `-` followed by `-x`, which would start a comment.
+fn merges(prev: &Token, next: &Token, src: &str) -> bool {
+    let Some(first) = next.text(src).chars().next() else {
+        return false;
+    };
+    match &prev.0 {
+        Name(name) if is_sym_name(name) => SYM_CHARS.contains(first),
+        Name(_) => first.is_alphanumeric() || matches!(first, '_' | '\'' | '!' | '?'),
+        IntLit(_) | FloatLit(_) => first.is_alphanumeric() || matches!(first, '_' | '.'),
+        _ => false,
+    }
+}
+
+/// Checks if a space goes between two tokens on the same line,
+/// given whether `prev` is a prefix operator applied to `next`.
+fn spaced(prev: &Token, next: &Token, prefix: bool, src: &str, ops: &OpTable) -> bool {
+    if merges(prev, next, src) {
+        return true;
+    }
+    match (&prev.0, &next.0) {
+        (Lp | Lb | Lc, _) | (_, Rp | Rb | Rc | Semicolon) => false,
+        (_, Name(name)) if name == "," || name == ":" => false,
+        (Name(name), _) | (_, Name(name)) if is_access(name, ops) => false,
+        _ => !prefix,
+    }
+}
+
+/// Reformats Lynx source with canonical spacing and indentation,
+/// or returns the first [`Error`] encountered if it does not parse.
+///
+/// Tokens are separated by single spaces, except inside brackets,
+/// before `,`, `:`, and `;`, around `.` and `::`, and after prefix operators.
+/// Lines are indented by their nesting in brackets,
+/// plus one level where they continue an expression from the line above.
+/// Line breaks and comments are kept as written,
+/// while consecutive blank lines are merged into one.
+///
+/// Formatting is idempotent: formatted source is formatted as is.
+///
+/// # Examples
+///
+/// ```
+/// use lynx::format::format;
+///
+/// let src = "x=1 +2;f  ( a, - b )\n\n\n{\nprint x;  -- note\n}";
+/// let formatted = "x = 1 + 2; f (a, -b)\n\n{\n    print x; -- note\n}\n";
+/// assert_eq!(format(src).unwrap(), formatted);
+/// assert_eq!(format(formatted).unwrap(), formatted);
+/// ```
+pub fn format(src: &str) -> Result<String, Error> {
+    let (tokens, trivia) = tokenize_with_trivia(src)?;
+    let config = ParserConfig::default();
+    parse(&tokens, &config)?;
+    let ops = &config.ops;
+    // Comments by line number, at most one per line
+    let comments: HashMap<_, _> = trivia
+        .iter()
+        .flat_map(|trivia| trivia.leading.iter().chain(&trivia.trailing))
+        .map(|Comment(text, span)| (span.0.0, text.trim_end()))
+        .collect();
+
+    let mut out = String::new();
+    let mut tokens = tokens
+        .iter()
+        .filter(|token| token.0 != BlankLine)
+        .peekable();
+    // Number of brackets open
+    let mut depth = 0usize;
+    // Last token written, and whether it is a prefix operator
+    let mut prev: Option<&Token> = None;
+    let mut prefix = false;
+    // Whether a blank line is due before the next line written
+    let mut blank = false;
+
+    for line_idx in 0..src.lines().count() {
+        let mut line_tokens = vec![];
+        while let Some(token) = tokens.next_if(|token| token.1.0.0 == line_idx + 1) {
+            line_tokens.push(token);
+        }
+        let comment = comments.get(&(line_idx + 1));
+
+        if line_tokens.is_empty() && comment.is_none() {
+            blank = !out.is_empty();
+            continue;
+        }
+        let continued = !blank && prev.is_some_and(|prev| !ends_item(prev));
+        if blank {
+            out.push('\n');
+            blank = false;
+        }
+
+        let closers = line_tokens
+            .iter()
+            .take_while(|token| matches!(token.0, Rp | Rb | Rc))
+            .count();
+        let indent = depth.saturating_sub(closers) + usize::from(continued && closers == 0);
+        out.push_str(&INDENT.repeat(indent));
+
+        for (idx, &token) in line_tokens.iter().enumerate() {
+            if idx > 0
+                && let Some(prev) = prev
+                && spaced(prev, token, prefix, src, ops)
+            {
+                out.push(' ');
+            }
+            out.push_str(token.text(src));
+
+            match token.0 {
+                Lp | Lb | Lc => depth += 1,
+                Rp | Rb | Rc => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            prefix = match &token.0 {
+                Name(name) if ops.prefix(name).is_some() => {
+                    ops.infix(name).is_none() || prev.is_none_or(expects_operand)
+                }
+                _ => false,
+            };
+            prev = Some(token);
+        }
+
+        if let Some(comment) = comment {
+            if !line_tokens.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(comment);
+        }
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_spacing() {
+        let cases = [
+            ("x=1+2 ;", "x = 1 + 2;\n"),
+            ("f(a ,b)", "f (a, b)\n"),
+            ("[ 1,2 ] ++ [3]", "[1, 2] ++ [3]\n"),
+            ("m . f x :: y", "m.f x::y\n"),
+            ("f - x; g (- x); - - x", "f - x; g (-x); - -x\n"),
+            ("println ! rb; map @ (A, B)", "println !rb; map @(A, B)\n"),
+            ("(n:Int)=>n", "(n: Int) => n\n"),
+            ("a `div`b", "a `div` b\n"),
+            ("(, x);(+)", "(, x); (+)\n"),
+            ("{ }", "{}\n"),
+        ];
+        for (src, formatted) in cases {
+            assert_eq!(format(src).unwrap(), formatted, "formatting {:?}", src);
+        }
+    }
+
+    #[test]
+    fn test_format_layout() {
+        let src = "\n\nfn (f (n: Int): Int) {\nif (n == 0) {1}\n        else {n * f (n-1)};\n};\n\n\n\n\
+                   s = \"a\"\n-- comment\n  \\\\raw  \n;\n\n";
+        let formatted = "fn (f (n: Int): Int) {\n    if (n == 0) {1}\n        else {n * f (n - 1)};\n};\n\n\
+                         s = \"a\"\n    -- comment\n    \\\\raw  \n    ;\n";
+        assert_eq!(format(src).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let src = "f x  -- trailing  \n  -- leading\n\t y;--tight\n\n\n-- footer";
+        let formatted = "f x -- trailing\n    -- leading\n    y; --tight\n\n-- footer\n";
+        assert_eq!(format(src).unwrap(), formatted);
+        // Comment markers in literals are not comments
+        assert_eq!(format("\"--\"  '-'").unwrap(), "\"--\" '-'\n");
+    }
+
+    #[test]
+    fn test_format_idempotent() {
+        let srcs = [
+            "x=1 +2;f  (a,b)\n\n\n  y = [\n1,\n  2\n  ]",
+            "match n {\n  1 | 2 => \"small\";  -- few\n\t_ => \"big\";\n};",
+            "println (do {\nprint \"1 + 2 = \";\n1;\n} + 2);",
+            "a = - 1; b = -(a); c = 1 .. 5; d = 0x1F",
+        ];
+        for src in srcs {
+            let formatted = format(src).unwrap();
+            assert_eq!(
+                format(&formatted).unwrap(),
+                formatted,
+                "formatting {:?}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_literals() {
+        // Literals are kept as written
+        let src = "[0x1F, 1_000, 1.5e3, '\\n', \"a\\tb\"]";
+        assert_eq!(format(src).unwrap(), format!("{}\n", src));
+    }
+
+    #[test]
+    fn test_format_error() {
+        assert!(format("f (a, b").is_err());
+        assert!(format("\"unterminated").is_err());
+        assert_eq!(format("").unwrap(), "");
+        assert_eq!(format("\n\n-- only\n\n").unwrap(), "-- only\n");
+    }
+}
@@ -0,0 +1,593 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    ast::{AtomKind, Expr},
+    error::{Error, ErrorKind, ErrorKind::*},
+    token::Span,
+};
+
+/// Type of a Lynx expression, as inferred by [`infer`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// Type variable, standing for any type, e.g. `a` in `a -> a`.
+    Var(usize),
+    /// Type without parameters, e.g. `Int`.
+    Con(&'static str),
+    /// Function type, e.g. `Int -> Bool`.
+    Func(Box<Type>, Box<Type>),
+}
+
+impl Type {
+    /// Creates a function type from `param` to `ret`.
+    fn func(param: Type, ret: Type) -> Type {
+        Type::Func(Box::new(param), Box::new(ret))
+    }
+
+    /// Collects the type variables in order of first appearance.
+    fn vars(&self, vars: &mut Vec<usize>) {
+        match self {
+            Type::Var(var) if !vars.contains(var) => vars.push(*var),
+            Type::Var(_) | Type::Con(_) => {}
+            Type::Func(param, ret) => {
+                param.vars(vars);
+                ret.vars(vars);
+            }
+        }
+    }
+
+    /// Formats the type, naming its variables as per `vars`.
+    fn fmt_with(&self, f: &mut fmt::Formatter<'_>, vars: &[usize]) -> fmt::Result {
+        match self {
+            Type::Var(var) => {
+                let idx = vars.iter().position(|v| v == var).unwrap_or(0);
+                // `a` to `z`, then `a1` to `z1`, and so on
+                write!(f, "{}", char::from(b'a' + (idx % 26) as u8))?;
+                if idx >= 26 {
+                    write!(f, "{}", idx / 26)?;
+                }
+                Ok(())
+            }
+            Type::Con(name) => write!(f, "{}", name),
+            Type::Func(param, ret) => {
+                // `->` is right-associative
+                if let Type::Func(..) = **param {
+                    write!(f, "(")?;
+                    param.fmt_with(f, vars)?;
+                    write!(f, ")")?;
+                } else {
+                    param.fmt_with(f, vars)?;
+                }
+                write!(f, " -> ")?;
+                ret.fmt_with(f, vars)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    /// Formats the type with its variables named `a`, `b`, `c`, ...
+    /// in order of first appearance, e.g. `a -> a`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut vars = Vec::new();
+        self.vars(&mut vars);
+        self.fmt_with(f, &vars)
+    }
+}
+
+/// Type scheme, i.e. a type generalized over some of its variables,
+/// e.g. `id` bound to `x => x` has the scheme `forall a. a -> a`.
+#[derive(Debug, Clone)]
+struct Scheme(
+    /// Generalized type variables, which are fresh at each use.
+    Vec<usize>,
+    /// Type.
+    Type,
+);
+
+/// Types the arithmetic operators apply to.
+const NUMERIC_TYPES: [&str; 2] = ["Int", "Float"];
+
+/// Type inferer for the subset of Lynx made of literals, names, lambdas,
+/// applications, bindings, and the `if` and `do` macros.
+///
+/// This is Hindley-Milner inference:
+/// lambda parameters are monomorphic, while bindings are generalized.
+///
+/// This type is an internal helper for [`infer`]
+/// and is *not* intended for public use.
+struct Inferer {
+    /// Stack of scopes, innermost last.
+    scopes: Vec<HashMap<String, Scheme>>,
+
+    /// Types the type variables have been solved to, indexed by variable.
+    subst: Vec<Option<Type>>,
+
+    /// Type variables restricted to [`NUMERIC_TYPES`],
+    /// e.g. the operand type of `+`.
+    numeric: HashSet<usize>,
+}
+
+impl Inferer {
+    /// Creates [`Inferer`] with the built-in names in scope.
+    fn new() -> Self {
+        let mut inferer = Self {
+            scopes: vec![HashMap::new()],
+            subst: Vec::new(),
+            numeric: HashSet::new(),
+        };
+
+        let bool = || Type::Con("Bool");
+        let str = || Type::Con("Str");
+        let binary =
+            |operand: Type, result: Type| Type::func(operand.clone(), Type::func(operand, result));
+        let mut builtins = vec![
+            ("true", Scheme(vec![], bool())),
+            ("false", Scheme(vec![], bool())),
+            ("++", Scheme(vec![], binary(str(), str()))),
+        ];
+        let var = inferer.fresh_numeric();
+        builtins.push((
+            "negate",
+            Scheme(vec![var.0], Type::func(var.1.clone(), var.1)),
+        ));
+        for op in ["+", "-", "*", "/", "%"] {
+            let var = inferer.fresh_numeric();
+            builtins.push((op, Scheme(vec![var.0], binary(var.1.clone(), var.1))));
+        }
+        for op in ["&&", "||"] {
+            builtins.push((op, Scheme(vec![], binary(bool(), bool()))));
+        }
+        for op in ["==", "!=", "<", ">", "<=", ">="] {
+            let var = inferer.fresh();
+            builtins.push((op, Scheme(vec![var.0], binary(Type::Var(var.0), bool()))));
+        }
+        inferer.scopes[0].extend(
+            builtins
+                .into_iter()
+                .map(|(name, scheme)| (name.to_string(), scheme)),
+        );
+        inferer
+    }
+
+    /// Creates a fresh type variable, returning its index along with it.
+    fn fresh(&mut self) -> (usize, Type) {
+        self.subst.push(None);
+        let var = self.subst.len() - 1;
+        (var, Type::Var(var))
+    }
+
+    /// Creates a fresh type variable restricted to [`NUMERIC_TYPES`],
+    /// returning its index along with it.
+    fn fresh_numeric(&mut self) -> (usize, Type) {
+        let var = self.fresh();
+        self.numeric.insert(var.0);
+        var
+    }
+
+    /// Follows solved type variables until the outermost structure is known.
+    fn prune(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match &self.subst[*var] {
+                Some(solved) => self.prune(solved),
+                None => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    /// Replaces all solved type variables in `ty` by their solutions.
+    fn resolve(&self, ty: &Type) -> Type {
+        match self.prune(ty) {
+            Type::Func(param, ret) => Type::func(self.resolve(&param), self.resolve(&ret)),
+            ty => ty,
+        }
+    }
+
+    /// Checks if type variable `var` occurs in `ty`.
+    fn occurs(&self, var: usize, ty: &Type) -> bool {
+        match self.prune(ty) {
+            Type::Var(other) => other == var,
+            Type::Con(_) => false,
+            Type::Func(param, ret) => self.occurs(var, &param) || self.occurs(var, &ret),
+        }
+    }
+
+    /// Unifies the type an expression is expected to have
+    /// with the type found for it at `span`.
+    fn unify(&mut self, expected: &Type, found: &Type, span: Span) -> Result<(), Error> {
+        self.unify_types(expected, found).map_err(|kind| {
+            let kind = kind.unwrap_or_else(|| {
+                let expected = self.resolve(expected).to_string();
+                let found = self.resolve(found).to_string();
+                MismatchedTypes(expected, found)
+            });
+            Error(kind, span)
+        })
+    }
+
+    /// Unifies two types, returning [`InfiniteType`] on failing the occurs check,
+    /// and nothing on a mismatch, to be reported by [`Self::unify`].
+    fn unify_types(&mut self, lhs: &Type, rhs: &Type) -> Result<(), Option<ErrorKind>> {
+        match (self.prune(lhs), self.prune(rhs)) {
+            (Type::Var(lhs), Type::Var(rhs)) if lhs == rhs => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => {
+                if self.occurs(var, &ty) {
+                    return Err(Some(InfiniteType));
+                }
+                if self.numeric.contains(&var) {
+                    match ty {
+                        Type::Var(other) => {
+                            self.numeric.insert(other);
+                        }
+                        Type::Con(name) if NUMERIC_TYPES.contains(&name) => {}
+                        _ => return Err(None),
+                    }
+                }
+                self.subst[var] = Some(ty);
+                Ok(())
+            }
+            (Type::Con(lhs), Type::Con(rhs)) if lhs == rhs => Ok(()),
+            (Type::Func(lhs_param, lhs_ret), Type::Func(rhs_param, rhs_ret)) => {
+                self.unify_types(&lhs_param, &rhs_param)?;
+                self.unify_types(&lhs_ret, &rhs_ret)
+            }
+            _ => Err(None),
+        }
+    }
+
+    /// Looks up a name from the innermost scope outwards.
+    fn lookup(&self, name: &str) -> Option<&Scheme> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Creates a copy of the scheme's type with fresh generalized variables.
+    fn instantiate(&mut self, Scheme(vars, ty): &Scheme) -> Type {
+        let fresh: HashMap<usize, Type> = vars
+            .iter()
+            .map(|var| {
+                let fresh = if self.numeric.contains(var) {
+                    self.fresh_numeric()
+                } else {
+                    self.fresh()
+                };
+                (*var, fresh.1)
+            })
+            .collect();
+        fn replace(ty: &Type, fresh: &HashMap<usize, Type>) -> Type {
+            match ty {
+                Type::Var(var) => fresh.get(var).cloned().unwrap_or(Type::Var(*var)),
+                Type::Con(_) => ty.clone(),
+                Type::Func(param, ret) => Type::func(replace(param, fresh), replace(ret, fresh)),
+            }
+        }
+        replace(&self.resolve(ty), &fresh)
+    }
+
+    /// Generalizes a type over the variables not free in any scope.
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut env_vars = Vec::new();
+        for Scheme(generalized, scheme_ty) in self.scopes.iter().flat_map(HashMap::values) {
+            let mut vars = Vec::new();
+            self.resolve(scheme_ty).vars(&mut vars);
+            env_vars.extend(vars.into_iter().filter(|var| !generalized.contains(var)));
+        }
+        let env_vars: HashSet<_> = env_vars.into_iter().collect();
+
+        let mut vars = Vec::new();
+        ty.vars(&mut vars);
+        vars.retain(|var| !env_vars.contains(var));
+        Scheme(vars, ty)
+    }
+
+    /// Infers the types of a sequence of expressions in the current scope,
+    /// returning that of the last one, or `Unit` if there is none.
+    fn infer_seq(&mut self, exprs: &[Expr]) -> Result<Type, Error> {
+        let mut ty = Type::Con("Unit");
+        for expr in exprs {
+            ty = self.infer(expr)?;
+        }
+        Ok(ty)
+    }
+
+    /// Infers the type of a block in a new scope.
+    fn infer_block(&mut self, exprs: &[Expr]) -> Result<Type, Error> {
+        self.scopes.push(HashMap::new());
+        let ty = self.infer_seq(exprs);
+        self.scopes.pop();
+        ty
+    }
+
+    /// Infers the type of an expression.
+    fn infer(&mut self, expr: &Expr) -> Result<Type, Error> {
+        match expr {
+            Expr::Atom(atom, span) => self.infer_atom(atom, *span),
+            Expr::App(_, _, span) => self.infer_app(expr, *span),
+            Expr::Tuple(_, span) | Expr::List(_, span) | Expr::Block(_, span) => {
+                Err(Error(UnsupportedExpr, *span))
+            }
+        }
+    }
+
+    /// Infers the type of an atom.
+    fn infer_atom(&mut self, atom: &AtomKind, span: Span) -> Result<Type, Error> {
+        match atom {
+            AtomKind::UnitLit => Ok(Type::Con("Unit")),
+            AtomKind::IntLit(_) => Ok(Type::Con("Int")),
+            AtomKind::FloatLit(_) => Ok(Type::Con("Float")),
+            AtomKind::CharLit(_) => Ok(Type::Con("Char")),
+            AtomKind::StrLit(_) => Ok(Type::Con("Str")),
+            AtomKind::Wildcard => Err(Error(UnsupportedExpr, span)),
+            AtomKind::Name(name) => match self.lookup(name).cloned() {
+                Some(scheme) => Ok(self.instantiate(&scheme)),
+                None => Err(Error(UnboundName(name.clone()), span)),
+            },
+        }
+    }
+
+    /// Infers the type of a function application,
+    /// which may be a binding, a lambda, a supported macro,
+    /// or a call to a function.
+    fn infer_app(&mut self, expr: &Expr, span: Span) -> Result<Type, Error> {
+        let (head, args) = expr.as_application();
+        let name = match head {
+            Expr::Atom(AtomKind::Name(name), _) => name.as_str(),
+            _ => "",
+        };
+
+        match (name, args.as_slice()) {
+            ("=", [Expr::Atom(AtomKind::Name(name), _), value]) => {
+                let ty = self.infer(value)?;
+                let scheme = self.generalize(&ty);
+                let scope = self
+                    .scopes
+                    .last_mut()
+                    .expect("there is always a top-level scope");
+                scope.insert(name.clone(), scheme);
+                Ok(Type::Con("Unit"))
+            }
+            // The function may be applied right away, e.g. `(x => x) 1`
+            ("=>", [param, body, args @ ..]) => {
+                let (_, param_ty) = self.fresh();
+                let mut scope = HashMap::new();
+                match param {
+                    Expr::Atom(AtomKind::Name(name), _) => {
+                        scope.insert(name.clone(), Scheme(vec![], param_ty.clone()));
+                    }
+                    Expr::Atom(AtomKind::Wildcard, _) => {}
+                    _ => return Err(Error(UnsupportedExpr, param.span())),
+                }
+                self.scopes.push(scope);
+                let body_ty = self.infer(body);
+                self.scopes.pop();
+                self.infer_call(Type::func(param_ty, body_ty?), args, span)
+            }
+            ("if", args) => self.infer_if(args, span),
+            ("do", [Expr::Block(exprs, _)]) => self.infer_block(exprs),
+            _ => {
+                let func_ty = self.infer(head)?;
+                self.infer_call(func_ty, &args, span)
+            }
+        }
+    }
+
+    /// Infers the type of a call to a function of type `func_ty`,
+    /// one argument at a time.
+    fn infer_call(&mut self, func_ty: Type, args: &[&Expr], span: Span) -> Result<Type, Error> {
+        let mut ty = func_ty;
+        for arg in args {
+            let arg_ty = self.infer(arg)?;
+            ty = match self.prune(&ty) {
+                Type::Func(param_ty, ret_ty) => {
+                    self.unify(&param_ty, &arg_ty, arg.span())?;
+                    *ret_ty
+                }
+                ty => {
+                    let (_, ret_ty) = self.fresh();
+                    self.unify(&Type::func(arg_ty, ret_ty.clone()), &ty, span)?;
+                    ret_ty
+                }
+            };
+        }
+        Ok(ty)
+    }
+
+    /// Infers the type of the arguments of the `if` macro,
+    /// i.e. `cond {...} [elif cond {...}]* [else {...}]`.
+    ///
+    /// Without `else`, the type is `Unit`, whatever the branches.
+    fn infer_if(&mut self, args: &[&Expr], span: Span) -> Result<Type, Error> {
+        Ok(self
+            .infer_branches(args, span)?
+            .unwrap_or(Type::Con("Unit")))
+    }
+
+    /// Infers the common type of the branches of the `if` macro,
+    /// or [`None`] if there is no `else` branch.
+    fn infer_branches(&mut self, args: &[&Expr], span: Span) -> Result<Option<Type>, Error> {
+        let [cond, Expr::Block(then_exprs, _), rest @ ..] = args else {
+            return Err(Error(UnsupportedExpr, span));
+        };
+        let cond_ty = self.infer(cond)?;
+        self.unify(&Type::Con("Bool"), &cond_ty, cond.span())?;
+        let then_ty = self.infer_block(then_exprs)?;
+
+        let (rest_ty, rest_span) = match rest {
+            [] => return Ok(None),
+            [
+                Expr::Atom(AtomKind::Name(kw), _),
+                Expr::Block(else_exprs, else_span),
+            ] if kw == "else" => (self.infer_block(else_exprs)?, *else_span),
+            [Expr::Atom(AtomKind::Name(kw), _), rest @ ..] if kw == "elif" => {
+                match self.infer_branches(rest, span)? {
+                    Some(rest_ty) => (
+                        rest_ty,
+                        Span(rest[0].span().0, rest[rest.len() - 1].span().1),
+                    ),
+                    None => return Ok(None),
+                }
+            }
+            _ => return Err(Error(UnsupportedExpr, span)),
+        };
+        self.unify(&then_ty, &rest_ty, rest_span)?;
+        Ok(Some(then_ty))
+    }
+}
+
+/// Infers the types of top-level Lynx expressions in order,
+/// returning either the type of each one or the first [`Error`] encountered.
+///
+/// Only literals, names, lambdas with a name or `_` as the parameter,
+/// applications, bindings to names, and the `if` and `do` macros are supported;
+/// anything else is an [`UnsupportedExpr`] error.
+/// Bindings are typed `Unit`, and the names they bind are generalized,
+/// e.g. `id = x => x` may be applied to both `1` and `true` afterwards.
+///
+/// Arithmetic operators apply to `Int` and `Float` alike,
+/// as long as both operands and the result share the type.
+/// A type variable so restricted is shown like any other,
+/// e.g. `x => x + x` is typed `a -> a`, though it cannot be applied to `true`.
+///
+/// # Examples
+///
+/// ```
+/// use lynx::{infer::infer, lexer::tokenize, parser::{ParserConfig, parse}};
+///
+/// let exprs = parse(&tokenize("id = x => x; id 1 == 2").unwrap(), &ParserConfig::default())
+///     .unwrap();
+/// let types: Vec<_> = infer(&exprs).unwrap().iter().map(|ty| ty.to_string()).collect();
+/// assert_eq!(types, vec!["Unit", "Bool"]);
+/// ```
+pub fn infer(exprs: &[Expr]) -> Result<Vec<Type>, Error> {
+    let mut inferer = Inferer::new();
+    exprs
+        .iter()
+        .map(|expr| {
+            let ty = inferer.infer(expr)?;
+            Ok(inferer.resolve(&ty))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::tokenize,
+        parser::{ParserConfig, parse},
+        token::Pos,
+    };
+
+    fn infer_str(src: &str) -> Result<Vec<String>, Error> {
+        let exprs = parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        Ok(infer(&exprs)?.iter().map(Type::to_string).collect())
+    }
+
+    #[test]
+    fn test_infer_literals() {
+        assert_eq!(
+            infer_str("1; 1.5; 'c'; \"s\"; (); true").unwrap(),
+            vec!["Int", "Float", "Char", "Str", "Unit", "Bool"]
+        );
+        assert_eq!(infer_str("1 + 2; 1 < 2").unwrap(), vec!["Int", "Bool"]);
+    }
+
+    #[test]
+    fn test_infer_arithmetic() {
+        assert_eq!(
+            infer_str("1.5 + 2.0; -1.5; 7 % 2; x => x * 2.0").unwrap(),
+            vec!["Float", "Float", "Int", "Float -> Float"]
+        );
+        // The restriction to numeric types survives generalization
+        assert_eq!(
+            infer_str("add = x => y => x + y; add; add 1 2; add 1.5 2.5").unwrap(),
+            vec!["Unit", "a -> a -> a", "Int", "Float"]
+        );
+        for src in [
+            "add = x => y => x + y; add true false",
+            "(x => x) + (x => x)",
+        ] {
+            assert!(
+                matches!(infer_str(src), Err(Error(MismatchedTypes(..), _))),
+                "inferring {:?}",
+                src
+            );
+        }
+        // Operands may not mix `Int` and `Float`
+        let Err(Error(MismatchedTypes(expected, found), _)) = infer_str("1 + 2.5") else {
+            panic!("expected type mismatch");
+        };
+        assert_eq!((expected.as_str(), found.as_str()), ("Int", "Float"));
+    }
+
+    #[test]
+    fn test_infer_lambdas() {
+        assert_eq!(infer_str("x => x").unwrap(), vec!["a -> a"]);
+        assert_eq!(infer_str("x => y => x").unwrap(), vec!["a -> b -> a"]);
+        assert_eq!(
+            infer_str("f => x => f (f x)").unwrap(),
+            vec!["(a -> a) -> a -> a"]
+        );
+        assert_eq!(infer_str("(x => x + 1) 2").unwrap(), vec!["Int"]);
+        assert_eq!(infer_str("_ => 1").unwrap(), vec!["a -> Int"]);
+    }
+
+    #[test]
+    fn test_infer_if() {
+        assert_eq!(
+            infer_str("c => if (c) {1} elif (c == false) {2} else {3}").unwrap(),
+            vec!["Bool -> Int"]
+        );
+        assert_eq!(
+            infer_str(
+                "if (true) {1} elif (false) {'c'}; if (true) {()} elif (false) {()} else {()}"
+            )
+            .unwrap(),
+            vec!["Unit", "Unit"]
+        );
+        assert!(matches!(
+            infer_str("if (true) {1} elif (false) {2} else {'c'}"),
+            Err(Error(MismatchedTypes(..), _))
+        ));
+    }
+
+    #[test]
+    fn test_let_generalization() {
+        assert_eq!(
+            infer_str("id = x => x; id 1; id true; do {k = x => _ => x; k 'c' 1}").unwrap(),
+            vec!["Unit", "Int", "Bool", "Char"]
+        );
+        // Lambda parameters are not generalized
+        let Err(Error(MismatchedTypes(expected, found), _)) = infer_str("f => do {f 1; f true}")
+        else {
+            panic!("expected type mismatch");
+        };
+        assert_eq!((expected.as_str(), found.as_str()), ("Int", "Bool"));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let Err(Error(MismatchedTypes(expected, found), span)) = infer_str("1 + true") else {
+            panic!("expected type mismatch");
+        };
+        assert_eq!((expected.as_str(), found.as_str()), ("Int", "Bool"));
+        assert_eq!(span, Span(Pos(1, 5), Pos(1, 8)));
+
+        let Err(Error(MismatchedTypes(_, found), span)) = infer_str("if (1) {2} else {3}") else {
+            panic!("expected type mismatch");
+        };
+        assert_eq!(found, "Int");
+        assert_eq!(span, Span(Pos(1, 5), Pos(1, 5)));
+
+        assert!(matches!(
+            infer_str("1 2"),
+            Err(Error(MismatchedTypes(..), _))
+        ));
+        assert!(matches!(infer_str("x => x x"), Err(Error(InfiniteType, _))));
+        assert!(matches!(infer_str("y"), Err(Error(UnboundName(_), _))));
+        assert!(matches!(infer_str("[1]"), Err(Error(UnsupportedExpr, _))));
+    }
+}
@@ -1,18 +1,40 @@
-use std::{iter::Peekable, str::Chars};
+use std::{
+    collections::HashMap,
+    iter::{Enumerate, Peekable},
+    num::IntErrorKind,
+    str::{Chars, Lines},
+    vec,
+};
 
 use crate::{
     error::{Error, ErrorKind::*},
-    token::{Pos, Span, Token, TokenKind::*},
+    token::{Comment, Pos, Span, Token, TokenKind, TokenKind::*, Trivia},
 };
 
 /// Characters allowed in symbolic names.
-const SYM_CHARS: &str = "~`!@#$%^&*-+=|\\:'<,>.?/";
+pub(crate) const SYM_CHARS: &str = "~`!@#$%^&*-+=|\\:'<,>.?/";
+
+/// Configuration of the lexer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LexerConfig {
+    /// Whether alphabetic names may end with `?`,
+    /// e.g. `empty?`, as is customary for predicates in some dialects.
+    pub predicate_names: bool,
+
+    /// Whether tab characters are rejected outside character/string literals,
+    /// for projects indenting with spaces only.
+    pub forbid_tabs: bool,
+
+    /// Whether comments are kept as trivia rather than dropped,
+    /// see [`tokenize_with_trivia`].
+    pub trivia: bool,
+}
 
 /// Lexer for a single line of Lynx source.
 ///
 /// Since no Lynx token spans multiple lines,
 /// the overall lexing task can be divided into independent per-line passes.
-/// This type is an internal helper for [`tokenize`]
+/// This type is an internal helper for [`Lexer`]
 /// and is *not* intended for public use.
 struct LineLexer<'a> {
     /// Peekable iterator over the characters in the line.
@@ -25,16 +47,30 @@ struct LineLexer<'a> {
     /// starts at `0` before any character is consumed,
     /// thus still `1`-based.
     col_no: usize,
+
+    /// Configuration of the lexer.
+    config: LexerConfig,
+
+    /// Comment ending the line, if any and [`LexerConfig::trivia`] is on.
+    comment: Option<Comment>,
+
+    /// Whether the text of names and string literals is kept;
+    /// if not, they are left empty, saving their allocations.
+    payloads: bool,
 }
 
 impl<'a> LineLexer<'a> {
-    /// Creates [`LineLexer`] from a single line of Lynx source
-    /// and the line number.
-    fn new(src: &'a str, line_no: usize) -> Self {
+    /// Creates [`LineLexer`] from a single line of Lynx source,
+    /// the line number, the configuration,
+    /// and whether to keep the text of names and string literals.
+    fn new(src: &'a str, line_no: usize, config: LexerConfig, payloads: bool) -> Self {
         Self {
             chars: src.chars().peekable(),
             line_no,
             col_no: 0,
+            config,
+            comment: None,
+            payloads,
         }
     }
 
@@ -50,27 +86,63 @@ impl<'a> LineLexer<'a> {
         Pos(self.line_no, self.col_no)
     }
 
+    /// Appends `c` to the text of a name or string literal,
+    /// unless [`Self::payloads`] is off.
+    fn push_payload(&self, s: &mut String, c: char) {
+        if self.payloads {
+            s.push(c);
+        }
+    }
+
+    /// Consumes the lookahead, which is a tab,
+    /// failing if tabs are forbidden.
+    fn advance_tab(&mut self) -> Result<(), Error> {
+        self.advance();
+        if self.config.forbid_tabs {
+            return Err(Error(TabChar, Span::point(self.pos())));
+        }
+        Ok(())
+    }
+
     /// Skips whitespace.
-    fn skip_ws(&mut self) {
+    fn skip_ws(&mut self) -> Result<(), Error> {
         while let Some(&c) = self.chars.peek() {
-            if !c.is_whitespace() {
-                break;
+            match c {
+                '\t' => self.advance_tab()?,
+                c if c.is_whitespace() => self.advance(),
+                _ => break,
             }
-            self.advance();
         }
+        Ok(())
     }
 
     /// Skips the rest of the line,
     /// invoked when the lookahead is `--`.
-    fn skip_line(&mut self) {
-        while let Some(_) = self.chars.peek() {
-            self.advance();
+    fn skip_line(&mut self) -> Result<(), Error> {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '\t' => self.advance_tab()?,
+                _ => self.advance(),
+            }
         }
+        Ok(())
+    }
+
+    /// Builds the error for a character/string literal delimited by `quote`
+    /// that is still open at the end of the line,
+    /// spanning from the opening quote to there.
+    fn unterminated(&self, quote: char, lit_start_pos: Pos) -> Error {
+        let kind = if quote == '\'' {
+            UnterminatedCharLit
+        } else {
+            UnterminatedStrLit
+        };
+        Error(kind, Span(lit_start_pos, self.pos()))
     }
 
-    /// Handles escape sequence in a character/string literal,
+    /// Handles escape sequence in a character/string literal delimited by `quote`,
     /// invoked when the lookahead is `\`.
-    fn handle_esc_seq(&mut self, lit_start_pos: Pos) -> Result<char, Error> {
+    fn handle_esc_seq(&mut self, quote: char, lit_start_pos: Pos) -> Result<char, Error> {
         self.advance(); // Skip `\`
         let esc_start_pos = self.pos();
 
@@ -131,18 +203,25 @@ impl<'a> LineLexer<'a> {
                             return Err(Error(UnknownEscapeSeq, Span(esc_start_pos, self.pos())));
                         }
                         None => {
-                            return Err(Error(
-                                UnterminatedCharOrStrLit,
-                                Span(lit_start_pos, self.pos()),
-                            ));
+                            return Err(self.unterminated(quote, lit_start_pos));
                         }
                     }
                 }
 
-                let code_point = u32::from_str_radix(&hex_str, 16)
-                    .map_err(|_| Error(UnknownEscapeSeq, Span(esc_start_pos, self.pos())))?;
-                char::from_u32(code_point)
-                    .ok_or_else(|| Error(UnknownEscapeSeq, Span(esc_start_pos, self.pos())))?
+                if hex_str.is_empty() {
+                    return Err(Error(UnknownEscapeSeq, Span(esc_start_pos, self.pos())));
+                }
+                self.decode_esc_digits(&hex_str, 16, esc_start_pos)?
+            }
+
+            // Byte escape sequences: `\xNN` and `\oNNN`
+            Some('x') => {
+                self.advance();
+                self.lex_esc_digits(2, 16, esc_start_pos)?
+            }
+            Some('o') => {
+                self.advance();
+                self.lex_esc_digits(3, 8, esc_start_pos)?
             }
 
             Some(_) => {
@@ -150,16 +229,51 @@ impl<'a> LineLexer<'a> {
                 return Err(Error(UnknownEscapeSeq, Span(esc_start_pos, self.pos())));
             }
             None => {
-                return Err(Error(
-                    UnterminatedCharOrStrLit,
-                    Span(lit_start_pos, self.pos()),
-                ));
+                return Err(self.unterminated(quote, lit_start_pos));
             }
         };
 
         Ok(escaped_ch)
     }
 
+    /// Lexes exactly `count` digits in `radix` of an escape sequence
+    /// starting at `esc_start_pos`, e.g. `1b` in `\x1b`,
+    /// and decodes them into the character of that value.
+    fn lex_esc_digits(
+        &mut self,
+        count: usize,
+        radix: u32,
+        esc_start_pos: Pos,
+    ) -> Result<char, Error> {
+        let mut digits = String::new();
+        while digits.len() < count {
+            match self.chars.peek() {
+                Some(&c) if c.is_digit(radix) => {
+                    self.advance();
+                    digits.push(c);
+                }
+                // The error spans only the partial sequence
+                _ => return Err(Error(UnknownEscapeSeq, Span(esc_start_pos, self.pos()))),
+            }
+        }
+        self.decode_esc_digits(&digits, radix, esc_start_pos)
+    }
+
+    /// Decodes the digits in `radix` of an escape sequence starting at `esc_start_pos`
+    /// into the character of that value.
+    fn decode_esc_digits(
+        &self,
+        digits: &str,
+        radix: u32,
+        esc_start_pos: Pos,
+    ) -> Result<char, Error> {
+        // Too many digits, a surrogate, or beyond `10FFFF`
+        u32::from_str_radix(digits, radix)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| Error(InvalidUnicodeEscape, Span(esc_start_pos, self.pos())))
+    }
+
     /// Lexes character literals,
     /// invoked when the lookahead is `'`.
     fn lex_char_lit(&mut self) -> Result<Token, Error> {
@@ -186,7 +300,7 @@ impl<'a> LineLexer<'a> {
 
                 Some('\\') => {
                     // Escape sequence
-                    let escaped_ch = self.handle_esc_seq(start_pos)?;
+                    let escaped_ch = self.handle_esc_seq('\'', start_pos)?;
                     ch_vec.push(escaped_ch);
                 }
 
@@ -196,7 +310,7 @@ impl<'a> LineLexer<'a> {
                 }
 
                 None => {
-                    return Err(Error(UnterminatedCharOrStrLit, Span(start_pos, self.pos())));
+                    return Err(self.unterminated('\'', start_pos));
                 }
             }
         }
@@ -218,17 +332,17 @@ impl<'a> LineLexer<'a> {
 
                 Some('\\') => {
                     // Escape sequence
-                    let escaped_ch = self.handle_esc_seq(start_pos)?;
-                    s.push(escaped_ch);
+                    let escaped_ch = self.handle_esc_seq('"', start_pos)?;
+                    self.push_payload(&mut s, escaped_ch);
                 }
 
                 Some(&c) => {
                     self.advance();
-                    s.push(c);
+                    self.push_payload(&mut s, c);
                 }
 
                 None => {
-                    return Err(Error(UnterminatedCharOrStrLit, Span(start_pos, self.pos())));
+                    return Err(self.unterminated('"', start_pos));
                 }
             }
         }
@@ -244,7 +358,7 @@ impl<'a> LineLexer<'a> {
 
         while let Some(&c) = self.chars.peek() {
             self.advance();
-            s.push(c);
+            self.push_payload(&mut s, c);
         }
 
         Token(StrLit(s), Span(start_pos, self.pos()))
@@ -298,18 +412,48 @@ impl<'a> LineLexer<'a> {
 
         while let Some(&c) = self.chars.peek() {
             match c {
+                // `_` may only separate two digits, e.g. not in `5_`, `5__0`, or `0x_1`
                 '_' => {
+                    let separates = num_str.ends_with(|c| Self::is_valid_digit(c, base))
+                        && self
+                            .chars
+                            .clone()
+                            .nth(1)
+                            .is_some_and(|c| Self::is_valid_digit(c, base));
                     self.advance(); // Skip `_` in number literals
+                    if !separates {
+                        return Err(Error(InvalidNumLitFormat, Span(start_pos, self.pos())));
+                    }
                 }
-                '.' if base == 10 => {
+                // Only decimal and hexadecimal numbers can be floating-point
+                // Not `..`, so that `1..5` is a range
+                '.' if (base == 10 || base == 16)
+                    && !is_float
+                    && self.chars.clone().nth(1) != Some('.') =>
+                {
                     self.advance();
-                    // Only decimal numbers can be floating-point
-                    if is_float {
-                        break;
-                    }
                     is_float = true;
                     num_str.push('.');
                 }
+                'e' | 'E' if base == 10 && self.starts_exponent() => {
+                    self.advance();
+                    num_str.push('e');
+                    if let Some(&sign @ ('+' | '-')) = self.chars.peek() {
+                        self.advance();
+                        num_str.push(sign);
+                    }
+                    is_float = true;
+                }
+                // Hexadecimal floats take a binary exponent, as `e` is a digit
+                'p' | 'P' if base == 16 && self.starts_exponent() => {
+                    self.advance();
+                    num_str.push('p');
+                    if let Some(&sign @ ('+' | '-')) = self.chars.peek() {
+                        self.advance();
+                        num_str.push(sign);
+                    }
+                    is_float = true;
+                }
                 c if Self::is_valid_digit(c, base) => {
                     self.advance();
                     num_str.push(c);
@@ -322,54 +466,170 @@ impl<'a> LineLexer<'a> {
 
         // Parse the number
         if is_float {
-            if let Ok(num) = num_str.parse::<f64>() {
-                Ok(Token(FloatLit(num), Span(start_pos, self.pos())))
+            let num = if base == 16 {
+                Self::parse_hex_float(&num_str)
             } else {
-                Err(Error(InvalidNumLitFormat, Span(start_pos, self.pos())))
+                num_str.parse::<f64>().ok()
+            };
+            match num {
+                Some(num) if num.is_finite() => {
+                    Ok(Token(FloatLit(num), Span(start_pos, self.pos())))
+                }
+                // Infinity has no literal to be printed back as
+                Some(_) => Err(Error(FloatLitTooLarge, Span(start_pos, self.pos()))),
+                None => Err(Error(InvalidNumLitFormat, Span(start_pos, self.pos()))),
             }
         } else {
-            if let Ok(num) = i64::from_str_radix(&num_str, base) {
-                Ok(Token(IntLit(num), Span(start_pos, self.pos())))
-            } else {
-                Err(Error(InvalidNumLitFormat, Span(start_pos, self.pos())))
+            match i64::from_str_radix(&num_str, base) {
+                Ok(num) => Ok(Token(IntLit(num), Span(start_pos, self.pos()))),
+                // `i64::MIN` is not representable either,
+                // since the literal is lexed before being negated
+                Err(err) if *err.kind() == IntErrorKind::PosOverflow => {
+                    Err(Error(IntLitTooLarge, Span(start_pos, self.pos())))
+                }
+                Err(_) => Err(Error(InvalidNumLitFormat, Span(start_pos, self.pos()))),
             }
         }
     }
 
+    /// Parses the digits of a hexadecimal float literal after `0x`, e.g. `1.8p3`,
+    /// where the binary exponent is mandatory, returning [`None`] if malformed
+    /// or if the exponent overflows.
+    fn parse_hex_float(num_str: &str) -> Option<f64> {
+        let (mantissa, exp) = num_str.split_once('p')?;
+        let exp: i32 = exp.parse().ok()?;
+        let (int_digits, frac_digits) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int_digits.is_empty() && frac_digits.is_empty() {
+            return None;
+        }
+        let mantissa = int_digits
+            .chars()
+            .chain(frac_digits.chars())
+            .filter_map(|c| c.to_digit(16))
+            .fold(0.0, |acc, digit| acc * 16.0 + digit as f64);
+        // Each fractional digit shifts the point by 4 bits
+        let shift = i32::try_from(frac_digits.len()).ok()?.checked_mul(4)?;
+        Some(mantissa * 2f64.powi(exp.checked_sub(shift)?))
+    }
+
+    /// Checks if the lookahead `e`, `E`, `p`, or `P` starts the exponent of a float,
+    /// i.e. it is followed by digits, optionally after a sign.
+    fn starts_exponent(&self) -> bool {
+        // Cloned to perform further lookaheads
+        let mut chars = self.chars.clone().skip(1);
+        match chars.next() {
+            Some('+' | '-') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
     /// Lexes alphabetic names,
     /// invoked when the lookahead is alphabetic or `_`.
     fn lex_alpha(&mut self, lookahead: char) -> Token {
         self.advance();
         let start_pos = self.pos();
+        let name = self.lex_alpha_rest(lookahead);
+        Token(Name(name), Span(start_pos, self.pos()))
+    }
+
+    /// Lexes the rest of an alphabetic name
+    /// after its first character `first`, which has been consumed.
+    fn lex_alpha_rest(&mut self, first: char) -> String {
         let mut name = String::new();
-        name.push(lookahead);
+        self.push_payload(&mut name, first);
 
         while let Some(&c) = self.chars.peek() {
             if !(c.is_alphanumeric() || c == '_' || c == '\'' || c == '!') {
                 break;
             }
             self.advance();
-            name.push(c);
+            self.push_payload(&mut name, c);
         }
 
-        Token(Name(name), Span(start_pos, self.pos()))
+        // Trailing `?` of predicate names
+        if self.config.predicate_names && self.chars.peek() == Some(&'?') {
+            self.advance();
+            self.push_payload(&mut name, '?');
+        }
+
+        name
+    }
+
+    /// Builds the error for a backtick name starting at `start_pos`
+    /// that is not closed right after its name,
+    /// depending on whether a closing backtick follows later in the line.
+    fn invalid_backtick_name(&mut self, start_pos: Pos) -> Error {
+        if !self.chars.clone().any(|c| c == '`') {
+            return Error(UnterminatedBacktickName, Span(start_pos, self.pos()));
+        }
+        while self.chars.peek() != Some(&'`') {
+            self.advance();
+        }
+        self.advance();
+        Error(InvalidBacktickName, Span(start_pos, self.pos()))
+    }
+
+    /// Handles lookahead `` ` ``.
+    fn lex_backtick(&mut self) -> Result<Token, Error> {
+        // Cloned to perform a second and third lookahead
+        let mut lookahead = self.chars.clone().skip(1);
+        match (lookahead.next(), lookahead.next()) {
+            // `` `name` ``: alphabetic name used as an infix operator,
+            // possibly qualified, e.g. `` `M.div` ``
+            (Some(c), _) if c.is_alphabetic() || c == '_' => {
+                self.advance();
+                let start_pos = self.pos();
+                self.advance();
+                let mut name = self.lex_alpha_rest(c);
+                // Cloned to perform a second lookahead
+                while let mut lookahead = self.chars.clone()
+                    && lookahead.next() == Some('.')
+                    && let Some(c) = lookahead.next()
+                    && (c.is_alphabetic() || c == '_')
+                {
+                    self.advance();
+                    self.advance();
+                    self.push_payload(&mut name, '.');
+                    name.push_str(&self.lex_alpha_rest(c));
+                }
+                if self.chars.peek() != Some(&'`') {
+                    return Err(self.invalid_backtick_name(start_pos));
+                }
+                self.advance();
+                Ok(Token(BacktickName(name), Span(start_pos, self.pos())))
+            }
+            // ``` `` ```: nothing in backticks
+            (Some('`'), c) if c.is_none_or(|c| !SYM_CHARS.contains(c)) => {
+                self.advance();
+                let start_pos = self.pos();
+                self.advance();
+                Err(Error(InvalidBacktickName, Span(start_pos, self.pos())))
+            }
+            // Otherwise: just a symbolic name
+            _ => Ok(self.lex_sym('`')),
+        }
     }
 
     /// Lexes symbolic names,
     /// invoked when the lookahead is among [`SYM_CHARS`]
     /// excluding `-`, `\`, and `'`.
+    ///
+    /// The whole run of [`SYM_CHARS`] makes up one name (maximal munch),
+    /// even if it starts with a shorter known operator:
+    /// since operators are user-definable, the lexer cannot know where to split.
     fn lex_sym(&mut self, lookahead: char) -> Token {
         self.advance();
         let start_pos = self.pos();
         let mut name = String::new();
-        name.push(lookahead);
+        self.push_payload(&mut name, lookahead);
 
         while let Some(&c) = self.chars.peek() {
             if !SYM_CHARS.contains(c) {
                 break;
             }
             self.advance();
-            name.push(c);
+            self.push_payload(&mut name, c);
         }
 
         Token(Name(name), Span(start_pos, self.pos()))
@@ -386,58 +646,63 @@ impl<'a> LineLexer<'a> {
                 Token(UnitLit, Span(start_pos, self.pos()))
             }
             // Otherwise: just a left parenthesis
-            _ => Token(Lp, Span(self.pos(), self.pos())),
+            _ => Token(Lp, Span::point(self.pos())),
         }
     }
 
     /// Handles lookahead `)`.
     fn lex_rp(&mut self) -> Token {
         self.advance();
-        Token(Rp, Span(self.pos(), self.pos()))
+        Token(Rp, Span::point(self.pos()))
     }
 
     /// Handles lookahead `[`.
     fn lex_lb(&mut self) -> Token {
         self.advance();
-        Token(Lb, Span(self.pos(), self.pos()))
+        Token(Lb, Span::point(self.pos()))
     }
 
     /// Handles lookahead `]`.
     fn lex_rb(&mut self) -> Token {
         self.advance();
-        Token(Rb, Span(self.pos(), self.pos()))
+        Token(Rb, Span::point(self.pos()))
     }
 
     /// Handles lookahead `{`.
     fn lex_lc(&mut self) -> Token {
         self.advance();
-        Token(Lc, Span(self.pos(), self.pos()))
+        Token(Lc, Span::point(self.pos()))
     }
 
     /// Handles lookahead `}`.
     fn lex_rc(&mut self) -> Token {
         self.advance();
-        Token(Rc, Span(self.pos(), self.pos()))
+        Token(Rc, Span::point(self.pos()))
     }
 
     /// Handles lookahead `;`.
     fn lex_semicolon(&mut self) -> Token {
         self.advance();
-        Token(Semicolon, Span(self.pos(), self.pos()))
+        Token(Semicolon, Span::point(self.pos()))
     }
 
     /// Handles lookahead `-`,
     /// returning [`None`] if a line comment is encountered.
-    fn lex_hyphen(&mut self) -> Option<Token> {
+    fn lex_hyphen(&mut self) -> Result<Option<Token>, Error> {
         // Cloned to perform a second lookahead
         match self.chars.clone().nth(1) {
             // `--`: line comment
             Some('-') => {
-                self.skip_line();
-                None
+                let start = Pos(self.line_no, self.col_no + 1);
+                let text: String = self.chars.clone().collect();
+                self.skip_line()?;
+                if self.config.trivia {
+                    self.comment = Some(Comment(text, Span(start, self.pos())));
+                }
+                Ok(None)
             }
             // Otherwise: just a symbolic name
-            _ => Some(self.lex_sym('-')),
+            _ => Ok(Some(self.lex_sym('-'))),
         }
     }
 
@@ -447,7 +712,8 @@ impl<'a> LineLexer<'a> {
         match self.chars.clone().nth(1) {
             // `\\`: raw string literal
             Some('\\') => self.lex_raw_string_lit(),
-            // Otherwise: just a symbolic name
+            // Otherwise: just a symbolic name,
+            // even at the end of the line, as there is no line continuation
             _ => self.lex_sym('\\'),
         }
     }
@@ -455,15 +721,15 @@ impl<'a> LineLexer<'a> {
     /// Handles unknown lookahead.
     fn lex_unknown(&mut self) -> Error {
         self.advance();
-        Error(UnexpectedChar, Span(self.pos(), self.pos()))
+        Error(UnexpectedChar, Span::point(self.pos()))
     }
 
     /// Lexes the line, returning either a [`Vec`] of all [`Token`]s
     /// or the first [`Error`] encountered.
-    pub fn tokenize(mut self) -> Result<Vec<Token>, Error> {
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, Error> {
         let mut tokens = Vec::new();
         loop {
-            self.skip_ws();
+            self.skip_ws()?;
 
             match self.chars.peek() {
                 None => {
@@ -479,11 +745,12 @@ impl<'a> LineLexer<'a> {
                         '{' => self.lex_lc(),
                         '}' => self.lex_rc(),
                         ';' => self.lex_semicolon(),
-                        '-' => match self.lex_hyphen() {
+                        '-' => match self.lex_hyphen()? {
                             Some(token) => token,
                             None => break,
                         },
                         '\\' => self.lex_backslash(),
+                        '`' => self.lex_backtick()?,
                         '\'' => self.lex_char_lit()?,
                         '"' => self.lex_quoted_str_lit()?,
                         c if c.is_ascii_digit() => self.lex_num_lit(c)?,
@@ -502,17 +769,292 @@ impl<'a> LineLexer<'a> {
     }
 }
 
+/// State carried from line to line when lexing Lynx source.
+///
+/// This type is an internal helper for [`Lexer`] and [`ChunkLexer`]
+/// and is *not* intended for public use.
+struct LineState {
+    /// Line number of the first blank line since the last token, if any.
+    blank_line_no: Option<usize>,
+
+    /// Whether any token has been lexed.
+    started: bool,
+
+    /// Configuration of the lexer.
+    config: LexerConfig,
+
+    /// Comments lexed so far, if [`LexerConfig::trivia`] is on.
+    comments: Vec<Comment>,
+
+    /// Whether the text of names and string literals is kept,
+    /// see [`LineLexer::payloads`].
+    payloads: bool,
+}
+
+impl LineState {
+    /// Creates [`LineState`] for the start of the source.
+    fn new(config: LexerConfig) -> Self {
+        Self {
+            blank_line_no: None,
+            started: false,
+            config,
+            comments: Vec::new(),
+            payloads: true,
+        }
+    }
+
+    /// Lexes the next line, numbered `line_no`,
+    /// returning its tokens preceded by the pending [`BlankLine`], if any.
+    fn lex_line(&mut self, line_str: &str, line_no: usize) -> Result<Vec<Token>, Error> {
+        if line_str.trim().is_empty() {
+            if self.config.forbid_tabs
+                && let Some(idx) = line_str.chars().position(|c| c == '\t')
+            {
+                return Err(Error(TabChar, Span::point(Pos(line_no, idx + 1))));
+            }
+            if self.started {
+                self.blank_line_no.get_or_insert(line_no);
+            }
+            return Ok(Vec::new());
+        }
+
+        let mut line_lexer = LineLexer::new(line_str, line_no, self.config, self.payloads);
+        let line_tokens = line_lexer.tokenize()?;
+        self.comments.extend(line_lexer.comment);
+        // Comment-only lines neither emit nor cancel a pending blank line
+        if line_tokens.is_empty() {
+            return Ok(line_tokens);
+        }
+        self.started = true;
+        match self.blank_line_no.take() {
+            Some(blank_line_no) => {
+                let blank_line = Token(BlankLine, Span::point(Pos(blank_line_no, 1)));
+                Ok(std::iter::once(blank_line).chain(line_tokens).collect())
+            }
+            None => Ok(line_tokens),
+        }
+    }
+}
+
+/// Lexer for Lynx source, yielding [`Token`]s lazily, one line at a time.
+///
+/// Consecutive blank lines between tokens are collapsed into one [`BlankLine`];
+/// those before the first token or after the last one are dropped.
+/// Iteration ends after the first [`Error`].
+///
+/// Only the current line is held in memory,
+/// so streaming consumers, e.g. highlighters, can process huge sources
+/// or stop early without paying for the rest;
+/// errors further down are then not detected either.
+/// Use [`tokenize`] when all tokens are needed anyway.
+pub struct Lexer<'a> {
+    /// Remaining lines of the source, with their `0`-based indices.
+    lines: Enumerate<Lines<'a>>,
+
+    /// Tokens of the current line not yet yielded.
+    line_tokens: vec::IntoIter<Token>,
+
+    /// State carried from line to line.
+    state: LineState,
+
+    /// Whether an error has been yielded.
+    failed: bool,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates [`Lexer`] from Lynx source with the default configuration.
+    pub fn new(src: &'a str) -> Self {
+        Self::with_config(src, LexerConfig::default())
+    }
+
+    /// Creates [`Lexer`] from Lynx source and the configuration.
+    pub fn with_config(src: &'a str, config: LexerConfig) -> Self {
+        Self {
+            lines: src.lines().enumerate(),
+            line_tokens: Vec::new().into_iter(),
+            state: LineState::new(config),
+            failed: false,
+        }
+    }
+
+    /// Counts the tokens by kind name (see [`TokenKind::name`]),
+    /// or returns the first [`Error`] encountered.
+    ///
+    /// Only the kinds are needed, so the text of names and string literals
+    /// is never built, saving an allocation per such token,
+    /// and each token is dropped as soon as it is counted.
+    pub fn count_tokens(mut self) -> Result<HashMap<&'static str, usize>, Error> {
+        self.state.payloads = false;
+        let mut counts = HashMap::new();
+        for token in self {
+            *counts.entry(token?.0.name()).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Checks that the source lexes, returning the first [`Error`] encountered.
+    ///
+    /// Each token is dropped as soon as it is lexed and nothing after the error is lexed,
+    /// so this is a cheap syntax gate, e.g. for pre-commit hooks.
+    pub fn validate_only(self) -> Result<(), Error> {
+        for token in self {
+            token?;
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator of token kinds paired with their spans,
+    /// which ends silently at the first [`Error`].
+    pub fn spanned(self) -> impl Iterator<Item = (TokenKind, Span)> + 'a {
+        self.map_while(|result| result.ok().map(|Token(kind, span)| (kind, span)))
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if let Some(token) = self.line_tokens.next() {
+                return Some(Ok(token));
+            }
+
+            let (line_idx, line_str) = self.lines.next()?;
+            match self.state.lex_line(line_str, line_idx + 1) {
+                Ok(line_tokens) => self.line_tokens = line_tokens.into_iter(),
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Lexer for Lynx source arriving in chunks, e.g. read from a socket.
+///
+/// No token spans multiple lines, so only complete lines are lexed:
+/// [`ChunkLexer::feed`] holds back the text after the last line break fed so far,
+/// which may end in the middle of a string literal or a comment,
+/// and [`ChunkLexer::finish`] lexes it as the last line.
+/// Positions count from the start of the first chunk,
+/// and the tokens are the same as those of [`tokenize`] on the whole source.
+///
+/// The lexer should not be fed any more after returning an [`Error`].
+pub struct ChunkLexer {
+    /// Text after the last line break fed so far.
+    buf: String,
+
+    /// Number of lines lexed so far.
+    line_count: usize,
+
+    /// State carried from line to line.
+    state: LineState,
+}
+
+impl ChunkLexer {
+    /// Creates [`ChunkLexer`] with the default configuration.
+    pub fn new() -> Self {
+        Self::with_config(LexerConfig::default())
+    }
+
+    /// Creates [`ChunkLexer`] with the configuration.
+    pub fn with_config(config: LexerConfig) -> Self {
+        Self {
+            buf: String::new(),
+            line_count: 0,
+            state: LineState::new(config),
+        }
+    }
+
+    /// Lexes the next line, with the line break removed.
+    fn lex_line(&mut self, line_str: &str) -> Result<Vec<Token>, Error> {
+        self.line_count += 1;
+        self.state.lex_line(line_str, self.line_count)
+    }
+
+    /// Feeds the next chunk of source,
+    /// returning the [`Token`]s of the lines it completes.
+    pub fn feed(&mut self, chunk: &str) -> Result<Vec<Token>, Error> {
+        self.buf.push_str(chunk);
+        let Some(end) = self.buf.rfind('\n') else {
+            return Ok(Vec::new());
+        };
+
+        let buf = std::mem::take(&mut self.buf);
+        let mut tokens = Vec::new();
+        for line_str in buf[..end].split('\n') {
+            // Like `str::lines`, which `Lexer` uses
+            let line_str = line_str.strip_suffix('\r').unwrap_or(line_str);
+            tokens.extend(self.lex_line(line_str)?);
+        }
+        self.buf = buf[end + 1..].to_string();
+        Ok(tokens)
+    }
+
+    /// Lexes the text held back after the last line break,
+    /// returning its [`Token`]s.
+    pub fn finish(mut self) -> Result<Vec<Token>, Error> {
+        if self.buf.is_empty() {
+            return Ok(Vec::new());
+        }
+        let buf = std::mem::take(&mut self.buf);
+        self.lex_line(&buf)
+    }
+}
+
+impl Default for ChunkLexer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Lexes Lynx source, returning either a [`Vec`] of all [`Token`]s
 /// or the first [`Error`] encountered.
+///
+/// This is the eager counterpart of [`Lexer`],
+/// convenient for the parser, which needs random access to the tokens,
+/// at the cost of allocating them all upfront.
 pub fn tokenize(src: &str) -> Result<Vec<Token>, Error> {
-    let mut tokens = Vec::new();
-    for (line_idx, line_str) in src.lines().enumerate() {
-        let line_no = line_idx + 1;
-        let line_lexer = LineLexer::new(line_str, line_no);
-        let line_tokens = line_lexer.tokenize()?;
-        tokens.extend(line_tokens);
+    Lexer::new(src).collect()
+}
+
+/// Lexes Lynx source like [`tokenize`],
+/// also returning the comments attached to each [`Token`] as [`Trivia`].
+///
+/// A comment following a token on the same line trails that token;
+/// any other comment, on a line of its own, leads the next token.
+/// The extra last [`Trivia`] holds the comments after all tokens as leading ones,
+/// so there is always one more [`Trivia`] than there are tokens.
+pub fn tokenize_with_trivia(src: &str) -> Result<(Vec<Token>, Vec<Trivia>), Error> {
+    let config = LexerConfig {
+        trivia: true,
+        ..Default::default()
+    };
+    let mut lexer = Lexer::with_config(src, config);
+    let tokens = lexer.by_ref().collect::<Result<Vec<_>, _>>()?;
+
+    let mut trivia = vec![Trivia::default(); tokens.len() + 1];
+    // Index of the first token after the comment
+    let mut idx = 0;
+    for comment in lexer.state.comments {
+        while let Some(token) = tokens.get(idx)
+            && token.1.0 < comment.1.0
+        {
+            idx += 1;
+        }
+        match idx.checked_sub(1) {
+            Some(prev) if tokens[prev].1.1.0 == comment.1.0.0 => {
+                trivia[prev].trailing = Some(comment)
+            }
+            _ => trivia[idx].leading.push(comment),
+        }
     }
-    Ok(tokens)
+    Ok((tokens, trivia))
 }
 
 #[cfg(test)]
@@ -524,6 +1066,17 @@ mod tests {
         tokens.into_iter().map(|Token(kind, _)| kind).collect()
     }
 
+    /// Lexes `chunks` with [`ChunkLexer`], collecting all tokens.
+    fn tokenize_chunks(chunks: &[&str]) -> Result<Vec<Token>, Error> {
+        let mut lexer = ChunkLexer::new();
+        let mut tokens = Vec::new();
+        for chunk in chunks {
+            tokens.extend(lexer.feed(chunk)?);
+        }
+        tokens.extend(lexer.finish()?);
+        Ok(tokens)
+    }
+
     #[test]
     fn test_empty_line() {
         let tokens = tokenize("").unwrap();
@@ -565,6 +1118,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_float_literals() {
         let tokens = tokenize("3.14 0.5 100.0").unwrap();
         let kinds = token_kinds(tokens);
@@ -615,6 +1169,45 @@ mod tests {
         assert_eq!(tokens.len(), 0);
     }
 
+    #[test]
+    fn test_no_block_comments() {
+        // `{-` opens a block starting with `-`, as in `match n {-1 => ...}`
+        let tokens = tokenize("{-1 -} x").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Lc,
+                Name("-".to_string()),
+                IntLit(1),
+                Name("-".to_string()),
+                Rc,
+                Name("x".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comment_markers_in_literals() {
+        let tokens = tokenize(r#""{- not -} -- a comment" '-'"#).unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![StrLit("{- not -} -- a comment".to_string()), CharLit('-')]
+        );
+
+        let tokens = tokenize(r"\\raw -- {-").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![StrLit("raw -- {-".to_string())]);
+    }
+
+    #[test]
+    fn test_quotes_in_comment() {
+        let tokens = tokenize("a -- \"unterminated 'x\nb").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![Name("a".to_string()), Name("b".to_string())]);
+    }
+
     #[test]
     fn test_char_literal_simple() {
         let tokens = tokenize("'a' 'Z' '0'").unwrap();
@@ -638,6 +1231,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_byte_escape_sequences() {
+        let tokens = tokenize(r#"'\x41' '\o101' "\x1b[0m\o033" '\xfF' '\o777'"#).unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                CharLit('A'),
+                CharLit('A'),
+                StrLit("\x1b[0m\x1b".to_string()),
+                CharLit('\u{FF}'),
+                CharLit('\u{1FF}')
+            ]
+        );
+        // Only as many digits as required are taken
+        let tokens = tokenize(r#""\x411\o0101""#).unwrap();
+        assert_eq!(token_kinds(tokens), vec![StrLit("A1\x081".to_string())]);
+
+        for src in [r"'\x4'", r"'\xg1'", r#""\o18""#, r"'\o7"] {
+            let result = tokenize(src);
+            assert!(
+                matches!(result, Err(Error(UnknownEscapeSeq, _))),
+                "lexing {:?}",
+                src
+            );
+        }
+        // The error spans only the partial sequence
+        let Err(Error(_, span)) = tokenize(r#""ab\x4""#) else {
+            panic!("expected unknown escape sequence");
+        };
+        assert_eq!(span, Span(Pos(1, 4), Pos(1, 6)));
+    }
+
     #[test]
     fn test_char_literal_quote_escapes() {
         let tokens = tokenize(r#"'\'' '\"'"#).unwrap();
@@ -666,8 +1292,14 @@ mod tests {
 
     #[test]
     fn test_unterminated_char_literal_error() {
-        let result = tokenize("'a");
-        assert!(matches!(result, Err(Error(UnterminatedCharOrStrLit, _))));
+        let result = tokenize("x = 'a  \ny");
+        let Err(Error(UnterminatedCharLit, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected unterminated character literal");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 5, 1, 8));
+
+        let result = tokenize("'\\");
+        assert!(matches!(result, Err(Error(UnterminatedCharLit, _))));
     }
 
     #[test]
@@ -711,10 +1343,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_backslash_at_end_of_line() {
+        let tokens = tokenize("\\").unwrap();
+        assert_eq!(token_kinds(tokens), vec![Name(r"\".to_string())]);
+
+        let tokens = tokenize("a \\\nb").unwrap();
+        assert_eq!(
+            token_kinds(tokens),
+            vec![
+                Name("a".to_string()),
+                Name(r"\".to_string()),
+                Name("b".to_string())
+            ]
+        );
+
+        let tokens = tokenize("a |\\").unwrap();
+        assert_eq!(
+            token_kinds(tokens),
+            vec![Name("a".to_string()), Name(r"|\".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_raw_string_literal_at_end_of_line() {
+        let tokens = tokenize("s = \\\\").unwrap();
+        assert_eq!(
+            token_kinds(tokens),
+            vec![
+                Name("s".to_string()),
+                Name("=".to_string()),
+                StrLit("".to_string())
+            ]
+        );
+
+        let tokens = tokenize("\\\\ends with \\").unwrap();
+        assert_eq!(
+            token_kinds(tokens),
+            vec![StrLit(r"ends with \".to_string())]
+        );
+    }
+
     #[test]
     fn test_unterminated_string_literal_error() {
-        let result = tokenize(r#""unterminated"#);
-        assert!(matches!(result, Err(Error(UnterminatedCharOrStrLit, _))));
+        let result = tokenize(r#"f "unterminated"#);
+        let Err(Error(UnterminatedStrLit, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected unterminated string literal");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 3, 1, 15));
+
+        let result = tokenize(r#""\u{41"#);
+        let Err(Error(UnterminatedStrLit, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected unterminated string literal");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 1, 1, 6));
     }
 
     #[test]
@@ -743,8 +1425,23 @@ mod tests {
 
     #[test]
     fn test_invalid_unicode_escape_invalid_codepoint() {
-        let result = tokenize(r"'\u{FFFFFF}'");
-        assert!(matches!(result, Err(Error(UnknownEscapeSeq, _))));
+        for src in [
+            r"'\u{FFFFFF}'",
+            r"'\u{D800}'",
+            r#""a\u{110000}""#,
+            r"'\u{100000000}'",
+        ] {
+            let result = tokenize(src);
+            assert!(
+                matches!(result, Err(Error(InvalidUnicodeEscape, _))),
+                "lexing {:?}",
+                src
+            );
+        }
+        let Err(Error(_, span)) = tokenize(r#""ab\u{DFFF}""#) else {
+            panic!("expected invalid unicode escape");
+        };
+        assert_eq!(span, Span(Pos(1, 4), Pos(1, 11)));
     }
 
     #[test]
@@ -786,6 +1483,344 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_blank_lines() {
+        let tokens = tokenize("a\n\n  \n\t\nb\n-- comment\n\nc").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Name("a".to_string()),
+                BlankLine,
+                Name("b".to_string()),
+                BlankLine,
+                Name("c".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leading_and_trailing_blank_lines() {
+        let tokens = tokenize("\n\n-- comment\n\na\n\n\n").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![Name("a".to_string())]);
+    }
+
+    #[test]
+    fn test_lexer_spanned() {
+        let pairs: Vec<_> = Lexer::new("f x\n\n(1)")
+            .spanned()
+            .map(|(kind, Span(Pos(l0, c0), Pos(l1, c1)))| (kind, (l0, c0, l1, c1)))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Name("f".to_string()), (1, 1, 1, 1)),
+                (Name("x".to_string()), (1, 3, 1, 3)),
+                (BlankLine, (2, 1, 2, 1)),
+                (Lp, (3, 1, 3, 1)),
+                (IntLit(1), (3, 2, 3, 2)),
+                (Rp, (3, 3, 3, 3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_line_boundaries() {
+        assert!(Lexer::new("").next().is_none());
+        // A trailing line break does not start another line
+        for src in ["a b\nc", "a b\nc\n", "a b\r\nc\r\n"] {
+            let spans: Vec<_> = Lexer::new(src).map(|token| token.unwrap().1).collect();
+            assert_eq!(
+                spans,
+                vec![
+                    Span::point(Pos(1, 1)),
+                    Span::point(Pos(1, 3)),
+                    Span::point(Pos(2, 1)),
+                ],
+                "lexing {:?}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_tokens() {
+        let src = "f (x, 'a') = [1, 2.5];\n\n-- comment\ng \"s\" ()";
+        let counts = Lexer::new(src).count_tokens().unwrap();
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![
+                ("BlankLine", 1),
+                ("CharLit", 1),
+                ("FloatLit", 1),
+                ("IntLit", 1),
+                ("Lb", 1),
+                ("Lp", 1),
+                ("Name", 6),
+                ("Rb", 1),
+                ("Rp", 1),
+                ("Semicolon", 1),
+                ("StrLit", 1),
+                ("UnitLit", 1),
+            ]
+        );
+
+        // The same counts as when the text is kept
+        let src = "a `M.div` b ++ \"\\t\"\n\\\\raw";
+        let mut expected = HashMap::new();
+        for Token(kind, _) in tokenize(src).unwrap() {
+            *expected.entry(kind.name()).or_insert(0) += 1;
+        }
+        assert_eq!(Lexer::new(src).count_tokens().unwrap(), expected);
+
+        let result = Lexer::new("a\n'bc'").count_tokens();
+        assert!(matches!(result, Err(Error(MultipleCharsInCharLit, _))));
+        let result = Lexer::new("s = \"\\q\"").count_tokens();
+        assert!(matches!(result, Err(Error(UnknownEscapeSeq, _))));
+    }
+
+    #[test]
+    fn test_chunk_lexer() {
+        let src = "s = \"hello, world\" -- greeting\r\n\n  \n-- note\nt = s ++ \"!\"\n";
+        let expected = format!("{:?}", tokenize(src).unwrap());
+        // Split at every position, e.g. in the middle of the string or the comment
+        for (idx, _) in src.char_indices() {
+            let tokens = tokenize_chunks(&[&src[..idx], &src[idx..]]).unwrap();
+            assert_eq!(format!("{:?}", tokens), expected);
+        }
+
+        let chunks = [
+            "s = \"hel",
+            "lo, world\" -- gree",
+            "ting\r\n\n  \n-- no",
+            "te\nt = s ++ \"!\"",
+        ];
+        let tokens = tokenize_chunks(&chunks).unwrap();
+        assert_eq!(format!("{:?}", tokens), expected);
+    }
+
+    #[test]
+    fn test_chunk_lexer_holds_back_last_line() {
+        let mut lexer = ChunkLexer::new();
+        assert!(lexer.feed("a = \"x").unwrap().is_empty());
+        assert_eq!(lexer.feed("\";\nb").unwrap().len(), 4);
+        assert_eq!(
+            token_kinds(lexer.finish().unwrap()),
+            vec![Name("b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_chunk_lexer_error() {
+        let mut lexer = ChunkLexer::new();
+        assert!(lexer.feed("a = 1\nb = \"x").is_ok());
+        let result = lexer.feed(" y\n");
+        assert!(matches!(
+            result,
+            Err(Error(UnterminatedStrLit, Span(Pos(2, 5), Pos(2, 8))))
+        ));
+    }
+
+    #[test]
+    fn test_lexer_is_lazy() {
+        // The invalid character at the end is never reached
+        let src = "a b c d e\n".repeat(10_000) + "§";
+        let tokens: Vec<_> = Lexer::new(&src).take(5).collect();
+        assert_eq!(tokens.len(), 5);
+        assert!(tokens.iter().all(Result::is_ok));
+        assert!(tokenize(&src).is_err());
+    }
+
+    #[test]
+    fn test_lexer_stops_after_error() {
+        let mut lexer = Lexer::new("a\n'\nb");
+        assert!(matches!(lexer.next(), Some(Ok(Token(Name(_), _)))));
+        assert!(matches!(lexer.next(), Some(Err(_))));
+        assert!(lexer.next().is_none());
+
+        assert_eq!(Lexer::new("a\n'\nb").spanned().count(), 1);
+    }
+
+    #[test]
+    fn test_predicate_names() {
+        let config = LexerConfig {
+            predicate_names: true,
+            ..Default::default()
+        };
+        let kinds: Vec<_> = Lexer::with_config("empty? xs; a?b; a ? b", config)
+            .map(|token| token.unwrap().0)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Name("empty?".to_string()),
+                Name("xs".to_string()),
+                Semicolon,
+                Name("a?".to_string()),
+                Name("b".to_string()),
+                Semicolon,
+                Name("a".to_string()),
+                Name("?".to_string()),
+                Name("b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_only() {
+        assert!(
+            Lexer::new("f (x, 'a') = [1, 2.5];\n\n-- comment")
+                .validate_only()
+                .is_ok()
+        );
+        // Only the first error is returned
+        let Err(Error(EmptyCharLit, span)) = Lexer::new("ok\nx = ''\ny = \"").validate_only()
+        else {
+            panic!("expected empty character literal");
+        };
+        assert_eq!(span.0, Pos(2, 5));
+    }
+
+    #[test]
+    fn test_trivia() {
+        let src = "-- header\n\nf x -- trailing\n  -- leading\n  y;\n-- footer";
+        let (tokens, trivia) = tokenize_with_trivia(src).unwrap();
+        assert_eq!(trivia.len(), tokens.len() + 1);
+        let comments: Vec<_> = tokens
+            .iter()
+            .zip(&trivia)
+            .map(|(token, trivia)| {
+                let leading: Vec<_> = trivia.leading.iter().map(|c| c.0.as_str()).collect();
+                let trailing = trivia.trailing.as_ref().map(|c| c.0.as_str());
+                (token.text(src), leading, trailing)
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                ("f", vec!["-- header"], None),
+                ("x", vec![], Some("-- trailing")),
+                ("y", vec!["-- leading"], None),
+                (";", vec![], None),
+            ]
+        );
+        let footer = &trivia[tokens.len()].leading[0];
+        assert_eq!(footer.0, "-- footer");
+        assert_eq!(footer.1, Span(Pos(6, 1), Pos(6, 9)));
+        assert_eq!(footer.1.text(src), "-- footer");
+
+        // Comments are dropped unless asked for
+        assert!(Lexer::new(src).state.comments.is_empty());
+    }
+
+    #[test]
+    fn test_forbid_tabs() {
+        let config = LexerConfig {
+            forbid_tabs: true,
+            ..Default::default()
+        };
+        let lex = |src| Lexer::with_config(src, config).collect::<Result<Vec<_>, _>>();
+
+        let Err(Error(TabChar, span)) = lex("x =\t1") else {
+            panic!("expected tab character");
+        };
+        assert_eq!(span, Span::point(Pos(1, 4)));
+        assert!(matches!(lex("x -- a\tb"), Err(Error(TabChar, _))));
+        let Err(Error(TabChar, span)) = lex("x\n \t\ny") else {
+            panic!("expected tab character");
+        };
+        assert_eq!(span, Span::point(Pos(2, 2)));
+        // Tabs in literals are fine
+        let tokens = lex("'\t' \"\t\" \\\\\t").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                CharLit('\t'),
+                StrLit("\t".to_string()),
+                StrLit("\t".to_string())
+            ]
+        );
+
+        // Otherwise, tabs are whitespace
+        let tokens = tokenize("x =\t1 --\t\n\t\ny").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Name("x".to_string()),
+                Name("=".to_string()),
+                IntLit(1),
+                BlankLine,
+                Name("y".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_predicate_names_off() {
+        let tokens = tokenize("empty? a ? b").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Name("empty".to_string()),
+                Name("?".to_string()),
+                Name("a".to_string()),
+                Name("?".to_string()),
+                Name("b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbolic_names_maximal_munch() {
+        let tokens = tokenize("->> => ::= a,-b").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Name("->>".to_string()),
+                Name("=>".to_string()),
+                Name("::=".to_string()),
+                Name("a".to_string()),
+                Name(",-".to_string()),
+                Name("b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equals_runs() {
+        // `=`, `=>`, `==`, and `==>` are told apart by the whole run, spaced or not
+        let tokens = tokenize("a=b; a=>b; a==b; a==>b; a = => b").unwrap();
+        let names: Vec<_> = token_kinds(tokens)
+            .into_iter()
+            .filter_map(|kind| match kind {
+                Name(name) if !name.starts_with(char::is_alphabetic) => Some(name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["=", "=>", "==", "==>", "=", "=>"]);
+    }
+
+    #[test]
+    fn test_arrow_runs() {
+        // `<-` is an ordinary symbolic name, never split into `<` and `-`
+        let tokens = tokenize("x <- xs; a<b; a->b; a<-b; a <--b").unwrap();
+        let names: Vec<_> = token_kinds(tokens)
+            .into_iter()
+            .filter_map(|kind| match kind {
+                Name(name) if !name.starts_with(char::is_alphabetic) => Some(name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["<-", "<", "->", "<-", "<--"]);
+    }
+
     #[test]
     fn test_hyphen_in_symbolic_name() {
         let tokens = tokenize("-").unwrap();
@@ -800,6 +1835,75 @@ mod tests {
         assert_eq!(kinds, vec![Name(r"\".to_string())]);
     }
 
+    #[test]
+    fn test_backtick_names() {
+        let tokens = tokenize("a `mod` b ``` ` `+`").unwrap();
+        let spans: Vec<_> = tokens
+            .iter()
+            .map(|Token(_, Span(Pos(_, c0), Pos(_, c1)))| (*c0, *c1))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![(1, 1), (3, 7), (9, 9), (11, 13), (15, 15), (17, 19)]
+        );
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Name("a".to_string()),
+                BacktickName("mod".to_string()),
+                Name("b".to_string()),
+                Name("```".to_string()),
+                Name("`".to_string()),
+                Name("`+`".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_backtick_name_error() {
+        let result = tokenize("a `div b");
+        assert!(matches!(
+            result,
+            Err(Error(UnterminatedBacktickName, Span(Pos(1, 3), Pos(1, 6))))
+        ));
+        let result = tokenize("a `div");
+        assert!(matches!(result, Err(Error(UnterminatedBacktickName, _))));
+    }
+
+    #[test]
+    fn test_qualified_backtick_names() {
+        let tokens = tokenize("a `M.div` b `M.N.f'`").unwrap();
+        assert_eq!(tokens[1].1, Span(Pos(1, 3), Pos(1, 9)));
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                Name("a".to_string()),
+                BacktickName("M.div".to_string()),
+                Name("b".to_string()),
+                BacktickName("M.N.f'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_backtick_name_error() {
+        let result = tokenize("a `` b");
+        assert!(matches!(
+            result,
+            Err(Error(InvalidBacktickName, Span(Pos(1, 3), Pos(1, 4))))
+        ));
+        let result = tokenize("a `div mod` b");
+        assert!(matches!(
+            result,
+            Err(Error(InvalidBacktickName, Span(Pos(1, 3), Pos(1, 11))))
+        ));
+        for src in ["a `M.` b", "a `M..div` b", "a `div+` b"] {
+            assert!(matches!(tokenize(src), Err(Error(InvalidBacktickName, _))));
+        }
+    }
+
     #[test]
     fn test_binary_literals() {
         let tokens = tokenize("0b1010 0b1111_0000 0B101").unwrap();
@@ -824,6 +1928,38 @@ mod tests {
         assert_eq!(kinds, vec![IntLit(0xFF), IntLit(0xDEAD_BEEF), IntLit(0x10)]);
     }
 
+    #[test]
+    fn test_hex_float_literals() {
+        let tokens = tokenize("0x1.8p3 0x1p-2 0XA.Bp+1 0x1_0.0_8P0").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                FloatLit(12.0),
+                FloatLit(0.25),
+                FloatLit(21.375),
+                FloatLit(16.03125)
+            ]
+        );
+
+        // The binary exponent is mandatory
+        let result = tokenize("0x1.8");
+        let Err(Error(InvalidNumLitFormat, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected invalid number literal format");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 1, 1, 5));
+        // The exponent, shifted by the fractional digits, must fit in 32 bits
+        let result = tokenize("0x1.8p-2147483648");
+        assert!(matches!(result, Err(Error(InvalidNumLitFormat, _))));
+        let tokens = tokenize("0x1p-2147483648").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![FloatLit(0.0)]);
+        // Not followed by digits: `p` is a name
+        let tokens = tokenize("0x1p").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![IntLit(1), Name("p".to_string())]);
+    }
+
     #[test]
     fn test_underscores_in_decimals() {
         let tokens = tokenize("1_000_000 1_2_3").unwrap();
@@ -838,10 +1974,141 @@ mod tests {
         assert_eq!(kinds, vec![FloatLit(1000.5), FloatLit(314.1592)]);
     }
 
+    #[test]
+    fn test_misplaced_underscores() {
+        // A leading underscore starts a name instead
+        let tokens = tokenize("_5").unwrap();
+        assert_eq!(token_kinds(tokens), vec![Name("_5".to_string())]);
+        let tokens = tokenize("0xF_F 0b1_0 2.718_5").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![IntLit(0xFF), IntLit(0b10), FloatLit(2.7185)]);
+
+        for src in ["5_", "5__0", "0x_1", "0b1_2", "1_.5", "1._5", "1_e5"] {
+            let result = tokenize(src);
+            assert!(
+                matches!(result, Err(Error(InvalidNumLitFormat, _))),
+                "lexing {:?}",
+                src
+            );
+        }
+        let Err(Error(_, span)) = tokenize("x = 1__0") else {
+            panic!("expected invalid number literal format");
+        };
+        assert_eq!(span, Span(Pos(1, 5), Pos(1, 6)));
+    }
+
+    #[test]
+    fn test_int_literal_bounds() {
+        let tokens = tokenize("9223372036854775807 0x7FFF_FFFF_FFFF_FFFF").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![IntLit(i64::MAX), IntLit(i64::MAX)]);
+
+        let result = tokenize("9223372036854775808");
+        let Err(Error(IntLitTooLarge, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected integer literal too large");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 1, 1, 19));
+        let result = tokenize("-9223372036854775808");
+        assert!(matches!(result, Err(Error(IntLitTooLarge, _))));
+        let result =
+            tokenize("0b1_0000000000000000000000000000000000000000000000000000000000000000");
+        assert!(matches!(result, Err(Error(IntLitTooLarge, _))));
+    }
+
+    #[test]
+    fn test_float_exponents() {
+        let tokens = tokenize("1.7976931348623157e308 1e3 2.5E-1 1_0e+0_1").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                FloatLit(f64::MAX),
+                FloatLit(1e3),
+                FloatLit(0.25),
+                FloatLit(100.0)
+            ]
+        );
+
+        let result = tokenize("1e999");
+        let Err(Error(FloatLitTooLarge, Span(Pos(l0, c0), Pos(l1, c1)))) = result else {
+            panic!("expected float literal too large");
+        };
+        assert_eq!((l0, c0, l1, c1), (1, 1, 1, 5));
+        let result = tokenize("1.7976931348623159e308");
+        assert!(matches!(result, Err(Error(FloatLitTooLarge, _))));
+        let result = tokenize("0x1p2000");
+        assert!(matches!(result, Err(Error(FloatLitTooLarge, _))));
+        // Underflowing is fine, rounding to zero
+        let tokens = tokenize("1e-999").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(kinds, vec![FloatLit(0.0)]);
+
+        // Not followed by digits: `e` is a name
+        let tokens = tokenize("2e 2e+ 0xE1").unwrap();
+        let kinds = token_kinds(tokens);
+        assert_eq!(
+            kinds,
+            vec![
+                IntLit(2),
+                Name("e".to_string()),
+                IntLit(2),
+                Name("e".to_string()),
+                Name("+".to_string()),
+                IntLit(0xE1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_num_literal_spans() {
+        let spans: Vec<_> = Lexer::new("0xFF 1.5e10 1.2.3")
+            .spanned()
+            .map(|(kind, Span(Pos(_, c0), Pos(_, c1)))| (kind, c0, c1))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (IntLit(0xFF), 1, 4),
+                (FloatLit(1.5e10), 6, 11),
+                (FloatLit(1.2), 13, 15),
+                (Name(".".to_string()), 16, 16),
+                (IntLit(3), 17, 17)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_num_literal_before_range() {
+        let tokens = tokenize("1..5 -3..0x3 1.5..2").unwrap();
+        let kinds = token_kinds(tokens);
+        let range = || Name("..".to_string());
+        assert_eq!(
+            kinds,
+            vec![
+                IntLit(1),
+                range(),
+                IntLit(5),
+                Name("-".to_string()),
+                IntLit(3),
+                range(),
+                IntLit(3),
+                FloatLit(1.5),
+                range(),
+                IntLit(2)
+            ]
+        );
+    }
+
     #[test]
     fn test_invalid_base_prefix_no_digits() {
-        let result = tokenize("0x");
-        assert!(matches!(result, Err(Error(InvalidNumLitFormat, _))));
+        for src in ["0x", "0o", "0b", "0x_", "0b2", "0o8"] {
+            let result = tokenize(src);
+            assert!(
+                matches!(result, Err(Error(InvalidNumLitFormat, _))),
+                "lexing {:?}",
+                src
+            );
+        }
     }
 
     #[test]
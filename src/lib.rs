@@ -0,0 +1,11 @@
+#[cfg(feature = "arena")]
+pub mod arena;
+pub mod ast;
+pub mod error;
+pub mod eval;
+pub mod format;
+pub mod infer;
+pub mod lexer;
+pub mod parser;
+pub mod token;
+pub mod validate;
@@ -1,17 +1,207 @@
-use crate::lexer::tokenize;
+use std::{process::ExitCode, time::Instant};
 
-mod ast;
-mod error;
-mod lexer;
-mod parser;
-mod token;
+use lynx::{
+    error::{ColumnMode, Diagnostics, Error},
+    eval::{Value, eval, eval_each, run_main},
+    lexer::tokenize,
+    parser::{ParserConfig, parse_with_diagnostics},
+    token::{Pos, Token},
+    validate::validate,
+};
 
-fn main() {
-    // TODO: Handle the situations where wrong args are given
-    let path = std::env::args_os().nth(1).unwrap();
-    let src = std::fs::read_to_string(path).expect("Failed to read file");
+const USAGE: &str = "Usage: lynx-lang [--eval | --script | --run | --emit-tokens-json | --dump-ast] [--time] [--max-errors <N>] <FILE>";
 
-    for token in tokenize(&src).unwrap() {
+/// Version of the schema printed by `--emit-tokens-json`,
+/// to be bumped whenever the schema changes incompatibly.
+const TOKENS_JSON_VERSION: u32 = 1;
+
+/// What the CLI does with the source.
+#[derive(PartialEq)]
+enum Mode {
+    /// Print the tokens.
+    Tokens,
+    /// Print the tokens as JSON, for external tooling.
+    TokensJson,
+    /// Print the top-level expressions as trees, for debugging the parser.
+    DumpAst,
+    /// Print the value of the last top-level expression.
+    Eval,
+    /// Print the value of every top-level expression other than `()`.
+    Script,
+    /// Print the result of running `main`.
+    Run,
+}
+
+/// Runs one phase of the pipeline,
+/// printing how long it took to stderr if `time` is set.
+fn timed<T>(time: bool, phase: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    if time {
+        eprintln!("{}: {:?}", phase, start.elapsed());
+    }
+    result
+}
+
+/// Lexes Lynx source and prints the tokens.
+fn print_tokens(src: &str, time: bool) -> Result<(), Error> {
+    for token in timed(time, "lex", || tokenize(src))? {
         println!("{}", token);
     }
+    Ok(())
+}
+
+/// Escapes `s` as a JSON string literal.
+fn json_str(s: &str) -> String {
+    let mut escaped = String::from('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if ch.is_control() => escaped.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Formats `pos` as a JSON object, along with its byte offset in `src`.
+fn json_pos(pos: Pos, src: &str) -> String {
+    format!(
+        "{{\"line\":{},\"col\":{},\"offset\":{}}}",
+        pos.0,
+        pos.1,
+        pos.byte_offset(src)
+    )
+}
+
+/// Formats `token` as a JSON object.
+fn json_token(token: &Token, src: &str) -> String {
+    format!(
+        "{{\"kind\":{},\"start\":{},\"end\":{},\"text\":{}}}",
+        json_str(token.0.name()),
+        json_pos(token.1.0, src),
+        json_pos(token.1.1, src),
+        json_str(token.text(src))
+    )
+}
+
+/// Lexes Lynx source and prints the tokens as a versioned JSON object.
+fn print_tokens_json(src: &str, time: bool) -> Result<(), Error> {
+    let tokens = timed(time, "lex", || tokenize(src))?;
+    let tokens: Vec<_> = tokens.iter().map(|token| json_token(token, src)).collect();
+    println!(
+        "{{\"version\":{},\"tokens\":[{}]}}",
+        TOKENS_JSON_VERSION,
+        tokens.join(",")
+    );
+    Ok(())
+}
+
+/// Lexes and parses Lynx source and prints the expressions as trees.
+fn print_trees(src: &str, time: bool, diags: &mut Diagnostics) -> Result<(), Error> {
+    let tokens = timed(time, "lex", || tokenize(src))?;
+    let exprs = timed(time, "parse", || {
+        parse_with_diagnostics(&tokens, &ParserConfig::default(), diags)
+    })?;
+    for expr in &exprs {
+        print!("{}", expr.tree());
+    }
+    Ok(())
+}
+
+/// Lexes, parses, and evaluates Lynx source,
+/// then prints the values as per `mode`.
+fn print_values(src: &str, mode: Mode, time: bool, diags: &mut Diagnostics) -> Result<(), Error> {
+    let tokens = timed(time, "lex", || tokenize(src))?;
+    let exprs = timed(time, "parse", || {
+        parse_with_diagnostics(&tokens, &ParserConfig::default(), diags)
+    })?;
+    validate(&exprs, diags);
+    match mode {
+        Mode::Script => eval_each(&exprs, |value| {
+            if value != Value::Unit {
+                println!("{}", value);
+            }
+        }),
+        Mode::Run => {
+            println!("{}", run_main(&exprs)?);
+            Ok(())
+        }
+        _ => {
+            println!("{}", eval(&exprs)?);
+            Ok(())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut mode = Mode::Tokens;
+    let mut time = false;
+    let mut max_errors = None;
+    let mut path = None;
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--eval") if mode == Mode::Tokens => mode = Mode::Eval,
+            Some("--script") if mode == Mode::Tokens => mode = Mode::Script,
+            Some("--run") if mode == Mode::Tokens => mode = Mode::Run,
+            Some("--emit-tokens-json") if mode == Mode::Tokens => mode = Mode::TokensJson,
+            Some("--dump-ast") if mode == Mode::Tokens => mode = Mode::DumpAst,
+            Some("--time") => time = true,
+            Some("--max-errors") if max_errors.is_none() => {
+                let Some(max) = args.next().and_then(|max| max.to_str()?.parse().ok()) else {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                };
+                max_errors = Some(max);
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let src = match std::fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", path.to_string_lossy(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut diags = Diagnostics::new();
+    if let Some(max) = max_errors {
+        diags.limit(max);
+    }
+    let result = match mode {
+        Mode::Tokens => print_tokens(&src, time),
+        Mode::TokensJson => print_tokens_json(&src, time),
+        Mode::DumpAst => print_trees(&src, time, &mut diags),
+        Mode::Eval | Mode::Script | Mode::Run => print_values(&src, mode, time, &mut diags),
+    };
+    if let Err(err) = result {
+        diags.emit(err);
+    }
+
+    #[cfg(feature = "unicode-width")]
+    let column_mode = ColumnMode::Display;
+    #[cfg(not(feature = "unicode-width"))]
+    let column_mode = ColumnMode::Chars;
+    eprint!("{}", diags.render_all_with(&src, column_mode));
+    if diags.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
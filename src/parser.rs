@@ -0,0 +1,1755 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    ast::{AtomKind, Expr, Module},
+    error::{Diagnostics, Error, ErrorKind::*},
+    lexer::tokenize,
+    token::{Pos, Span, Token, TokenKind, TokenKind::*},
+};
+
+/// Precedence of function application.
+///
+/// Infix operators with a higher precedence, such as `.` and `::`,
+/// bind tighter than application: `m.f x` is `(m.f) x`.
+pub const APP_PREC: u8 = 100;
+
+/// Precedence of `,`, which builds tuples instead of nested applications.
+pub const TUPLE_PREC: u8 = 20;
+
+/// Fixity of infix operators absent from [`OpTable`],
+/// including names in backticks, e.g. `` a `div` b ``,
+/// binding tighter than all standard operators but looser than application.
+pub const DEFAULT_FIXITY: Fixity = Fixity(Assoc::Left, 90);
+
+/// Maximum depth to which expressions may be nested,
+/// e.g. in parentheses or under prefix operators,
+/// so that parsing untrusted source cannot overflow the stack.
+pub const MAX_NESTING: usize = 128;
+
+/// Maximum depth of the tree of an expression (see [`Expr::depth`]),
+/// e.g. `3` for `a + b`, limiting long chains of operators or applications,
+/// so that walking the tree, even to drop it, cannot overflow the stack either.
+pub const MAX_DEPTH: usize = 256;
+
+/// Associativity of an infix operator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+    /// Non-associative, i.e. the operator cannot be chained without parentheses.
+    None,
+}
+
+/// Fixity of an infix operator.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixity(
+    /// Associativity.
+    pub Assoc,
+    /// Precedence; the higher, the tighter.
+    pub u8,
+);
+
+/// Table of operators known to the parser.
+#[derive(Debug)]
+pub struct OpTable {
+    /// Infix operators and their fixities.
+    infix: HashMap<String, Fixity>,
+
+    /// Prefix operators and the names they desugar to.
+    prefix: HashMap<String, String>,
+
+    /// Deprecated infix operators and their replacements,
+    /// which they are parsed as, with a warning.
+    deprecated: HashMap<String, String>,
+}
+
+impl OpTable {
+    /// Returns the fixity of an infix operator.
+    pub fn infix(&self, name: &str) -> Option<Fixity> {
+        self.infix.get(name).copied()
+    }
+
+    /// Returns the name a prefix operator desugars to.
+    pub fn prefix(&self, name: &str) -> Option<&str> {
+        self.prefix.get(name).map(String::as_str)
+    }
+
+    /// Returns the replacement of a deprecated infix operator.
+    pub fn replacement(&self, name: &str) -> Option<&str> {
+        self.deprecated.get(name).map(String::as_str)
+    }
+
+    /// Deprecates infix operator `name` in favor of `replacement`,
+    /// which it is parsed as from then on, with a warning.
+    pub fn deprecate(&mut self, name: &str, replacement: &str) {
+        self.deprecated
+            .insert(name.to_string(), replacement.to_string());
+    }
+
+    /// Returns the prefix operator that desugars to `func`.
+    pub fn prefix_op(&self, func: &str) -> Option<&str> {
+        self.prefix
+            .iter()
+            .find(|(_, prefix_func)| *prefix_func == func)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl Default for OpTable {
+    /// Creates [`OpTable`] with the standard operators.
+    fn default() -> Self {
+        use Assoc::*;
+
+        let infix = [
+            ("=", Right, 10),
+            (":=", Right, 10),
+            ("<<", Left, 10),
+            ("=>", Right, 15),
+            ("|>", Left, 25),
+            (":", Left, 28),
+            ("~", Left, 29),
+            ("|", Left, 30),
+            ("->", Right, 32),
+            ("||", Right, 34),
+            ("&&", Right, 36),
+            ("==", None, 40),
+            ("!=", None, 40),
+            ("<", None, 40),
+            (">", None, 40),
+            ("<=", None, 40),
+            (">=", None, 40),
+            ("++", Right, 50),
+            ("..", Left, 55),
+            ("+", Left, 60),
+            ("-", Left, 60),
+            ("*", Left, 70),
+            ("/", Left, 70),
+            ("%", Left, 70),
+            (".", Left, 110),
+            ("::", Left, 120),
+        ]
+        .into_iter()
+        .map(|(name, assoc, prec)| (name.to_string(), Fixity(assoc, prec)))
+        .collect();
+
+        let prefix = [("-", "negate"), ("!", "!"), ("@", "@"), ("&", "&")]
+            .into_iter()
+            .map(|(name, func)| (name.to_string(), func.to_string()))
+            .collect();
+
+        Self {
+            infix,
+            prefix,
+            deprecated: HashMap::new(),
+        }
+    }
+}
+
+/// Policy on what separates the expressions in a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SepPolicy {
+    /// Only `;` separates expressions; blank lines are insignificant.
+    Semicolon,
+    /// Both `;` and blank lines separate expressions.
+    #[default]
+    SemicolonOrBlankLine,
+}
+
+/// Configuration of the parser.
+#[derive(Debug, Default)]
+pub struct ParserConfig {
+    /// Operators known to the parser.
+    pub ops: OpTable,
+
+    /// Policy on what separates expressions.
+    pub sep: SepPolicy,
+}
+
+/// Checks if a name is symbolic rather than alphabetic.
+pub fn is_sym_name(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+/// Returns how a closing bracket is referred to in errors.
+fn closing_label(closing: &TokenKind) -> &'static str {
+    match closing {
+        Rp => "`)`",
+        Rb => "`]`",
+        Rc => "`}`",
+        _ => "closing bracket",
+    }
+}
+
+/// Fails if an expression built at `span` would be `depth` deep,
+/// exceeding [`MAX_DEPTH`].
+fn check_depth(depth: usize, span: Span) -> Result<(), Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error(NestingTooDeep, span));
+    }
+    Ok(())
+}
+
+/// Builds the application of binary operator `op` to `lhs` and `rhs`.
+fn binary(op: String, op_span: Span, lhs: Expr, rhs: Expr) -> Expr {
+    apply_binary(Expr::Atom(AtomKind::Name(op), op_span), lhs, rhs)
+}
+
+/// Builds the application of operator expression `op` to `lhs` and `rhs`.
+fn apply_binary(op: Expr, lhs: Expr, rhs: Expr) -> Expr {
+    let lhs_span = lhs.span();
+    let span = Span(lhs_span.0, rhs.span().1);
+    let op_span = op.span();
+    Expr::App(
+        Box::new(Expr::App(
+            Box::new(op),
+            Box::new(lhs),
+            Span(lhs_span.0, op_span.1),
+        )),
+        Box::new(rhs),
+        span,
+    )
+}
+
+/// Builds the operator of a backtick name spanning `span`,
+/// which is an access with `.` if qualified,
+/// e.g. `M.div` for `` `M.div` ``.
+fn backtick_op(name: &str, span: Span) -> Expr {
+    let line_no = span.0.0;
+    // Column of the first character after the backtick or `.`
+    let mut col_no = span.0.1 + 1;
+    let mut segments = name.split('.').map(|segment| {
+        let start = Pos(line_no, col_no);
+        col_no += segment.chars().count();
+        let end = Pos(line_no, col_no - 1);
+        col_no += 1;
+        Expr::Atom(AtomKind::Name(segment.to_string()), Span(start, end))
+    });
+    let first = segments
+        .next()
+        .expect("`split` yields at least one segment");
+    segments.fold(first, |op, segment| {
+        let Pos(line_no, col_no) = segment.span().0;
+        binary(
+            ".".to_string(),
+            Span::point(Pos(line_no, col_no - 1)),
+            op,
+            segment,
+        )
+    })
+}
+
+/// Cursor over the tokens fed to the parser.
+///
+/// This type is an internal helper for [`parse`]
+/// and is *not* intended for public use.
+struct TokenStream<'a> {
+    /// All tokens, consumed or not.
+    tokens: &'a [Token],
+
+    /// Index of the lookahead.
+    idx: usize,
+
+    /// Whether [`BlankLine`] tokens are skipped as insignificant.
+    skip_blank_lines: bool,
+
+    /// Whether [`BlankLine`] tokens are skipped inside each open bracket
+    /// enclosing the lookahead, innermost last.
+    brackets: Vec<bool>,
+
+    /// What would have been valid in place of the lookahead,
+    /// as registered since the last token was consumed.
+    expected: BTreeSet<&'static str>,
+}
+
+impl<'a> TokenStream<'a> {
+    /// Creates [`TokenStream`] from a slice of tokens.
+    fn new(tokens: &'a [Token], skip_blank_lines: bool) -> Self {
+        let mut stream = Self {
+            tokens,
+            idx: 0,
+            skip_blank_lines,
+            brackets: Vec::new(),
+            expected: BTreeSet::new(),
+        };
+        stream.skip_insignificant();
+        stream
+    }
+
+    /// Checks if a token is significant, i.e. not to be skipped.
+    ///
+    /// Blank lines are never significant inside parentheses and brackets,
+    /// which contain no sequences for them to separate,
+    /// so that these may span paragraphs.
+    fn is_significant(&self, token: &Token) -> bool {
+        let skip_blank_lines = self
+            .brackets
+            .last()
+            .copied()
+            .unwrap_or(self.skip_blank_lines);
+        !(skip_blank_lines && matches!(token, Token(BlankLine, _)))
+    }
+
+    /// Skips insignificant tokens before the lookahead.
+    fn skip_insignificant(&mut self) {
+        while let Some(token) = self.tokens.get(self.idx)
+            && !self.is_significant(token)
+        {
+            self.idx += 1;
+        }
+    }
+
+    /// Returns the lookahead without consuming it.
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.idx)
+    }
+
+    /// Returns the tokens not consumed yet, starting at the lookahead.
+    fn remaining(&self) -> &'a [Token] {
+        &self.tokens[self.idx..]
+    }
+
+    /// Returns the token after the lookahead without consuming anything.
+    fn peek_second(&self) -> Option<&'a Token> {
+        self.tokens[self.idx..]
+            .iter()
+            .filter(|token| self.is_significant(token))
+            .nth(1)
+    }
+
+    /// Checks if the tokens from the lookahead up to the matching `)`
+    /// make up the rest of a tuple section, e.g. `, x)`,
+    /// i.e. if an element is missing before `,` or before `)` after `,`.
+    fn starts_tuple_section(&self) -> bool {
+        let mut depth = 0;
+        // Whether the lookahead is `(` or `,` at the top level, and if the latter
+        let (mut after_sep, mut after_comma) = (true, false);
+
+        for Token(kind, _) in self.tokens[self.idx..].iter() {
+            match kind {
+                Lp | Lb | Lc => depth += 1,
+                Rp | Rb | Rc if depth > 0 => depth -= 1,
+                Rp => return after_comma,
+                Name(name) if name == "," && depth == 0 => {
+                    if after_sep {
+                        return true;
+                    }
+                    (after_sep, after_comma) = (true, true);
+                    continue;
+                }
+                Rb | Rc => return false,
+                // `;` within a nested block does not end the parentheses
+                Semicolon if depth == 0 => return false,
+                _ => {}
+            }
+            (after_sep, after_comma) = (false, false);
+        }
+        false
+    }
+
+    /// Consumes and returns the lookahead.
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.idx)?;
+        self.idx += 1;
+        match token.0 {
+            Lp | Lb => self.brackets.push(true),
+            Lc => self.brackets.push(self.skip_blank_lines),
+            Rp | Rb | Rc => {
+                self.brackets.pop();
+            }
+            _ => {}
+        }
+        self.skip_insignificant();
+        self.expected.clear();
+        Some(token)
+    }
+
+    /// Consumes and returns the lookahead if it is of any of `kinds`,
+    /// regardless of the values they carry;
+    /// otherwise, fails as per [`Self::unexpected`].
+    fn expect_one_of(&mut self, kinds: &[TokenKind]) -> Result<&'a Token, Error> {
+        match self.peek() {
+            Some(Token(found, _)) if kinds.iter().any(|kind| kind.same_kind(found)) => {
+                Ok(self.next().expect("the lookahead exists"))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Registers `what` as valid in place of the lookahead.
+    fn expect(&mut self, what: &'static str) {
+        self.expected.insert(what);
+    }
+
+    /// Builds the error for an unexpected lookahead,
+    /// listing everything registered as valid in place of it.
+    fn unexpected(&self) -> Error {
+        let expected = self.expected.iter().copied().collect();
+        match self.peek() {
+            Some(Token(kind, span)) => Error(UnexpectedToken(kind.to_string(), expected), *span),
+            None => Error(UnexpectedEof(expected), self.eof_span()),
+        }
+    }
+
+    /// Returns the position right after the last token,
+    /// used to report unexpected end of input.
+    fn eof_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(Token(_, Span(_, Pos(line_no, col_no)))) => Span::point(Pos(*line_no, col_no + 1)),
+            None => Span::point(Pos(1, 1)),
+        }
+    }
+}
+
+/// Parser for Lynx tokens.
+///
+/// Expressions are parsed with the Pratt algorithm,
+/// driven by the fixities in [`OpTable`].
+/// This type is an internal helper for [`parse`]
+/// and is *not* intended for public use.
+struct Parser<'a> {
+    /// Tokens to parse.
+    tokens: TokenStream<'a>,
+
+    /// Operators known to the parser.
+    ops: &'a OpTable,
+
+    /// Collector of warnings.
+    diags: &'a mut Diagnostics,
+
+    /// Depth of nesting of the expression being parsed.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Creates [`Parser`] from a slice of tokens, the configuration,
+    /// and the collector of warnings.
+    fn new(tokens: &'a [Token], config: &'a ParserConfig, diags: &'a mut Diagnostics) -> Self {
+        let skip_blank_lines = config.sep == SepPolicy::Semicolon;
+        Self {
+            tokens: TokenStream::new(tokens, skip_blank_lines),
+            ops: &config.ops,
+            diags,
+            depth: 0,
+        }
+    }
+
+    /// Runs `parse` one level deeper in the nesting of expressions,
+    /// failing instead if that would exceed [`MAX_NESTING`].
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth == MAX_NESTING {
+            let span = match self.tokens.peek() {
+                Some(Token(_, span)) => *span,
+                None => self.tokens.eof_span(),
+            };
+            return Err(Error(NestingTooDeep, span));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Consumes the lookahead, which must be `closing`,
+    /// returning the span of it.
+    fn expect_closing(&mut self, closing: &TokenKind) -> Result<Span, Error> {
+        self.tokens.expect(closing_label(closing));
+        let Token(_, span) = self.tokens.expect_one_of(std::slice::from_ref(closing))?;
+        Ok(*span)
+    }
+
+    /// Parses a sequence of expressions separated by `;` (or blank lines),
+    /// stopping before `closing`, or at the end of input if it is [`None`].
+    fn parse_seq(&mut self, closing: Option<&TokenKind>) -> Result<Vec<Expr>, Error> {
+        let mut exprs = Vec::new();
+        // Whether the sequence has started or just seen a separator
+        let mut after_sep = true;
+
+        loop {
+            match self.tokens.peek() {
+                None => match closing {
+                    Some(closing) => {
+                        self.tokens.expect(closing_label(closing));
+                        return Err(self.tokens.unexpected());
+                    }
+                    None => break,
+                },
+                Some(Token(kind, _)) if Some(kind) == closing => break,
+                Some(Token(kind @ (Semicolon | BlankLine), span)) => {
+                    // Empty expression
+                    if *kind == Semicolon && after_sep {
+                        self.diags.emit(Error(RedundantSemicolon, *span));
+                    }
+                    self.tokens.next();
+                    after_sep = true;
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            exprs.push(self.parse_expr(0)?);
+
+            match self.tokens.peek() {
+                None => {}
+                Some(Token(Semicolon | BlankLine, _)) => {
+                    self.tokens.next();
+                }
+                Some(Token(kind, _)) if Some(kind) == closing => {}
+                Some(_) => {
+                    self.tokens.expect("`;`");
+                    if !self.tokens.skip_blank_lines {
+                        self.tokens.expect("blank line");
+                    }
+                    if let Some(closing) = closing {
+                        self.tokens.expect(closing_label(closing));
+                    }
+                    return Err(self.tokens.unexpected());
+                }
+            }
+        }
+
+        Ok(exprs)
+    }
+
+    /// Parses an expression whose infix operators
+    /// have a precedence of at least `min_prec`.
+    fn parse_expr(&mut self, min_prec: u8) -> Result<Expr, Error> {
+        let mut lhs = self.nested(Self::parse_prefix)?;
+        // Updated as the chain goes on, rather than measuring `lhs` over and over
+        let mut depth = lhs.depth();
+        check_depth(depth, lhs.span())?;
+
+        while let Some(token @ Token(Name(written) | BacktickName(written), op_span)) =
+            self.tokens.peek()
+        {
+            let qualified = matches!(token.0, BacktickName(_)) && written.contains('.');
+            let written = written.as_str();
+            let name = self.ops.replacement(written).unwrap_or(written);
+            let fixity = if name == "," {
+                Fixity(Assoc::Left, TUPLE_PREC)
+            } else {
+                self.ops.infix(name).unwrap_or(DEFAULT_FIXITY)
+            };
+
+            let Fixity(assoc, prec) = fixity;
+            if prec < min_prec {
+                break;
+            }
+            self.tokens.next();
+            if name != written {
+                let replacement = name.to_string();
+                self.diags
+                    .emit(Error(DeprecatedSyntax(replacement), *op_span));
+            }
+
+            if name == "," {
+                lhs = self.parse_tuple_rest(lhs)?;
+                depth = lhs.depth();
+                check_depth(depth, lhs.span())?;
+                continue;
+            }
+
+            let rhs_min_prec = match assoc {
+                Assoc::Left | Assoc::None => prec + 1,
+                Assoc::Right => prec,
+            };
+            let rhs = self.nested(|parser| parser.parse_expr(rhs_min_prec))?;
+
+            if assoc == Assoc::None
+                && let Some(Token(Name(next), next_span)) = self.tokens.peek()
+                && let Some(Fixity(Assoc::None, next_prec)) = self.ops.infix(next)
+                && next_prec == prec
+            {
+                // Parse the rest of the chain to suggest splitting it,
+                // e.g. `a < b && b < c` for `a < b < c`
+                self.tokens.next();
+                let next_rhs = self.parse_expr(prec + 1)?;
+                let span = Span(lhs.span().0, next_rhs.span().1);
+                let first = binary(name.to_string(), *op_span, lhs, rhs.clone());
+                let second = binary(next.clone(), *next_span, rhs, next_rhs);
+                let hint = binary("&&".to_string(), *next_span, first, second);
+                let hint = hint.pretty(self.ops).to_string();
+                return Err(Error(ChainedComparison(hint), span));
+            }
+
+            let op = if qualified {
+                backtick_op(name, *op_span)
+            } else {
+                Expr::Atom(AtomKind::Name(name.to_string()), *op_span)
+            };
+            depth = (depth.max(op.depth()) + 1).max(rhs.depth()) + 1;
+            check_depth(depth, *op_span)?;
+            lhs = apply_binary(op, lhs, rhs);
+        }
+
+        self.tokens.expect("operator");
+        Ok(lhs)
+    }
+
+    /// Parses the rest of a tuple after its first element and `,`.
+    fn parse_tuple_rest(&mut self, first: Expr) -> Result<Expr, Error> {
+        let mut elems = vec![first];
+
+        loop {
+            elems.push(self.parse_expr(TUPLE_PREC + 1)?);
+            match self.tokens.peek() {
+                Some(Token(Name(name), _)) if name == "," => {
+                    self.tokens.next();
+                }
+                _ => break,
+            }
+        }
+
+        // A tuple has at least two elements, so the span always exists
+        let span = Span::join_all(elems.iter().map(Expr::span)).unwrap();
+        Ok(Expr::Tuple(elems, span))
+    }
+
+    /// Parses an expression that may start with prefix operators.
+    fn parse_prefix(&mut self) -> Result<Expr, Error> {
+        if let Some(Token(Name(name), op_span)) = self.tokens.peek()
+            && let Some(func) = self.ops.prefix(name)
+        {
+            self.tokens.next();
+            let operand = self.nested(Self::parse_prefix)?;
+            let span = Span(op_span.0, operand.span().1);
+            let func = Expr::Atom(AtomKind::Name(func.to_string()), *op_span);
+            return Ok(Expr::App(Box::new(func), Box::new(operand), span));
+        }
+
+        self.parse_app()
+    }
+
+    /// Checks if the lookahead may start an argument of function application.
+    fn starts_arg(&self) -> bool {
+        match self.tokens.peek() {
+            Some(Token(UnitLit | IntLit(_) | FloatLit(_) | CharLit(_) | StrLit(_), _)) => true,
+            Some(Token(Lp | Lb | Lc, _)) => true,
+            // Prefix operators that are also infix are treated as the latter,
+            // e.g. `f -x` is `f - x`
+            Some(Token(Name(name), _)) if is_sym_name(name) => {
+                self.ops.prefix(name).is_some() && self.ops.infix(name).is_none()
+            }
+            Some(Token(Name(_), _)) => true,
+            _ => false,
+        }
+    }
+
+    /// Parses a chain of function applications.
+    fn parse_app(&mut self) -> Result<Expr, Error> {
+        let mut func = self.parse_access()?;
+        let mut depth = func.depth();
+
+        while self.starts_arg() {
+            let arg = self.parse_arg()?;
+            depth = depth.max(arg.depth()) + 1;
+            check_depth(depth, arg.span())?;
+            let span = Span(func.span().0, arg.span().1);
+            func = Expr::App(Box::new(func), Box::new(arg), span);
+        }
+
+        self.tokens.expect("argument");
+        Ok(func)
+    }
+
+    /// Parses an argument of function application.
+    fn parse_arg(&mut self) -> Result<Expr, Error> {
+        if let Some(Token(Name(name), op_span)) = self.tokens.peek()
+            && let Some(func) = self.ops.prefix(name)
+        {
+            self.tokens.next();
+            let operand = self.nested(Self::parse_arg)?;
+            let span = Span(op_span.0, operand.span().1);
+            let func = Expr::Atom(AtomKind::Name(func.to_string()), *op_span);
+            return Ok(Expr::App(Box::new(func), Box::new(operand), span));
+        }
+
+        self.parse_access()
+    }
+
+    /// Parses an atom followed by infix operators
+    /// binding tighter than function application, e.g. `m.f`.
+    fn parse_access(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.parse_atom()?;
+        let mut depth = lhs.depth();
+
+        while let Some(Token(Name(name), op_span)) = self.tokens.peek()
+            && let Some(Fixity(_, prec)) = self.ops.infix(name)
+            && prec > APP_PREC
+        {
+            self.tokens.next();
+            let rhs = self.parse_atom()?;
+            depth = (depth + 1).max(rhs.depth()) + 1;
+            check_depth(depth, *op_span)?;
+            lhs = binary(name.clone(), *op_span, lhs, rhs);
+        }
+
+        Ok(lhs)
+    }
+
+    /// Parses an atom, or an expression delimited by brackets.
+    fn parse_atom(&mut self) -> Result<Expr, Error> {
+        let Some(Token(kind, span)) = self.tokens.next() else {
+            self.tokens.expect("expression");
+            return Err(self.tokens.unexpected());
+        };
+        let span = *span;
+
+        match kind {
+            UnitLit => Ok(Expr::Atom(AtomKind::UnitLit, span)),
+            IntLit(value) => Ok(Expr::Atom(AtomKind::IntLit(*value), span)),
+            FloatLit(value) => Ok(Expr::Atom(AtomKind::FloatLit(*value), span)),
+            CharLit(value) => Ok(Expr::Atom(AtomKind::CharLit(*value), span)),
+            StrLit(value) => Ok(self.parse_str_rest(value.clone(), span)),
+            Name(name) if name == "_" => Ok(Expr::Atom(AtomKind::Wildcard, span)),
+            Name(name) if !is_sym_name(name) => Ok(Expr::Atom(AtomKind::Name(name.clone()), span)),
+            Lp => self.parse_paren_rest(span),
+            Lb => self.parse_list_rest(span),
+            Lc => self.parse_block_rest(span),
+            Name(_) | BacktickName(_) | Rp | Rb | Rc | Semicolon | BlankLine => {
+                Err(Error(ExpectedExpr, span))
+            }
+        }
+    }
+
+    /// Concatenates adjacent string literals after the first one,
+    /// with line breaks inserted between.
+    fn parse_str_rest(&mut self, mut s: String, start_span: Span) -> Expr {
+        let mut span = start_span;
+
+        while let Some(Token(StrLit(next), next_span)) = self.tokens.peek() {
+            self.tokens.next();
+            s.push('\n');
+            s.push_str(next);
+            span.1 = next_span.1;
+        }
+
+        Expr::Atom(AtomKind::StrLit(s), span)
+    }
+
+    /// Parses the rest of a parenthesized expression after `(`.
+    fn parse_paren_rest(&mut self, lp_span: Span) -> Result<Expr, Error> {
+        // `(op)`: operator used as a name
+        if let (Some(Token(Name(name), _)), Some(Token(Rp, rp_span))) =
+            (self.tokens.peek(), self.tokens.peek_second())
+            && is_sym_name(name)
+        {
+            self.tokens.next();
+            self.tokens.next();
+            let span = Span(lp_span.0, rp_span.1);
+            return Ok(Expr::Atom(AtomKind::Name(name.clone()), span));
+        }
+
+        if self.tokens.starts_tuple_section() {
+            return self.parse_tuple_section_rest(lp_span);
+        }
+
+        // Parentheses only group, so `(x)` is just `x`
+        let expr = self.parse_expr(0)?;
+        self.expect_closing(&Rp)?;
+        Ok(expr)
+    }
+
+    /// Parses the rest of a tuple section after `(`,
+    /// desugaring it into a function of the missing elements, left to right,
+    /// e.g. `(, x)` into `#0 => (#0, x)` and `(, x,)` into `#0 => #1 => (#0, x, #1)`.
+    ///
+    /// The parameters are named so that they cannot clash with names in the source,
+    /// since `#0` lexes as `#` followed by `0`.
+    fn parse_tuple_section_rest(&mut self, lp_span: Span) -> Result<Expr, Error> {
+        let mut elems = Vec::new();
+        let mut params = Vec::new();
+
+        loop {
+            // A missing element, right before `,` or `)`
+            if let Some(Token(kind, span)) = self.tokens.peek()
+                && (*kind == Rp || matches!(kind, Name(name) if name == ","))
+            {
+                let param = format!("#{}", params.len());
+                let param = Expr::Atom(AtomKind::Name(param), Span::point(span.0));
+                elems.push(param.clone());
+                params.push(param);
+            } else {
+                elems.push(self.parse_expr(TUPLE_PREC + 1)?);
+            }
+
+            self.tokens.expect("`,`");
+            match self.tokens.peek() {
+                Some(Token(Name(name), _)) if name == "," => {
+                    self.tokens.next();
+                }
+                _ => break,
+            }
+        }
+
+        let rp_span = self.expect_closing(&Rp)?;
+        let span = Span(lp_span.0, rp_span.1);
+        // Each parameter adds a lambda, two applications deep
+        let depth = elems.iter().map(Expr::depth).max().unwrap_or_default() + 1;
+        check_depth(depth + 2 * params.len(), span)?;
+        let section = params
+            .into_iter()
+            .rev()
+            .fold(Expr::Tuple(elems, span), |body, param| {
+                let arrow = Expr::Atom(AtomKind::Name("=>".to_string()), span);
+                let func = Expr::App(Box::new(arrow), Box::new(param), span);
+                Expr::App(Box::new(func), Box::new(body), span)
+            });
+        Ok(section)
+    }
+
+    /// Parses the rest of a list after `[`.
+    fn parse_list_rest(&mut self, lb_span: Span) -> Result<Expr, Error> {
+        let mut elems = Vec::new();
+
+        if let Some(Token(Rb, rb_span)) = self.tokens.peek() {
+            self.tokens.next();
+            return Ok(Expr::List(elems, Span(lb_span.0, rb_span.1)));
+        }
+
+        loop {
+            elems.push(self.parse_expr(TUPLE_PREC + 1)?);
+            match self.tokens.peek() {
+                Some(Token(Name(name), _)) if name == "," => {
+                    self.tokens.next();
+                }
+                _ => break,
+            }
+        }
+
+        let rb_span = self.expect_closing(&Rb)?;
+        Ok(Expr::List(elems, Span(lb_span.0, rb_span.1)))
+    }
+
+    /// Parses the rest of a block after `{`.
+    fn parse_block_rest(&mut self, lc_span: Span) -> Result<Expr, Error> {
+        let exprs = self.parse_seq(Some(&Rc))?;
+        let rc_span = self.expect_closing(&Rc)?;
+        Ok(Expr::Block(exprs, Span(lc_span.0, rc_span.1)))
+    }
+}
+
+/// Parses Lynx tokens, returning either a [`Vec`] of all top-level [`Expr`]s
+/// or the first [`Error`] encountered.
+///
+/// Warnings are discarded; see [`parse_with_diagnostics`] to keep them.
+pub fn parse(tokens: &[Token], config: &ParserConfig) -> Result<Vec<Expr>, Error> {
+    parse_with_diagnostics(tokens, config, &mut Diagnostics::new())
+}
+
+/// Parses Lynx tokens like [`parse`] into a [`Module`],
+/// whose span covers the whole source from the first token to the last,
+/// including any trailing `;`, for diagnostics referring to the module itself.
+pub fn parse_module(tokens: &[Token], config: &ParserConfig) -> Result<Module, Error> {
+    let exprs = parse(tokens, config)?;
+    Ok(Module(exprs, module_span(tokens)))
+}
+
+/// Returns the span of a module from the first token to the last,
+/// or a dummy one if there are no tokens.
+fn module_span(tokens: &[Token]) -> Span {
+    let spans = tokens.iter().map(|Token(_, span)| *span);
+    Span::join_all(spans).unwrap_or_default()
+}
+
+/// Lexes and parses Lynx source into a [`Module`] without ever panicking,
+/// whatever the input, for tools processing untrusted source.
+///
+/// Returns the module, or [`None`] if there is an error,
+/// along with all diagnostics emitted, sorted by position.
+/// Expressions nested deeper than [`MAX_NESTING`],
+/// or whose trees are deeper than [`MAX_DEPTH`], e.g. long chains of operators,
+/// are an error rather than overflowing the stack.
+///
+/// # Examples
+///
+/// ```
+/// use lynx::parser::parse_source;
+///
+/// let (module, diags) = parse_source("f x;;");
+/// assert_eq!(module.unwrap().0.len(), 1);
+/// assert_eq!(diags[0].0.to_string(), "redundant semicolon");
+///
+/// let (module, diags) = parse_source(")");
+/// assert!(module.is_none());
+/// assert_eq!(diags[0].0.to_string(), "expected expression");
+/// ```
+pub fn parse_source(src: &str) -> (Option<Module>, Vec<Error>) {
+    let mut diags = Diagnostics::new();
+    let module = tokenize(src).and_then(|tokens| {
+        let exprs = parse_with_diagnostics(&tokens, &ParserConfig::default(), &mut diags)?;
+        Ok(Module(exprs, module_span(&tokens)))
+    });
+    let module = match module {
+        Ok(module) => Some(module),
+        Err(err) => {
+            diags.emit(err);
+            None
+        }
+    };
+    (module, diags.into_vec())
+}
+
+/// Lexes and parses Lynx source consisting of a single type,
+/// for tooling and tests working with types in isolation.
+///
+/// Types are ordinary expressions in Lynx,
+/// so this accepts any single expression, but nothing after it.
+///
+/// # Examples
+///
+/// ```
+/// use lynx::parser::{OpTable, parse_type};
+///
+/// let ops = OpTable::default();
+/// for src in ["a -> b", "[Int]", "(a -> b) -> c"] {
+///     assert_eq!(parse_type(src).unwrap().pretty(&ops).to_string(), src);
+/// }
+/// // Parentheses around a top-level tuple are not needed
+/// assert_eq!(parse_type("(Int, Bool)").unwrap().pretty(&ops).to_string(), "Int, Bool");
+/// assert!(parse_type("Int Bool;").is_err());
+/// ```
+pub fn parse_type(src: &str) -> Result<Expr, Error> {
+    parse_single(src)
+}
+
+/// Lexes and parses Lynx source consisting of a single pattern,
+/// for tooling and tests working with patterns in isolation.
+///
+/// Patterns are ordinary expressions in Lynx,
+/// so this accepts any single expression, but nothing after it.
+///
+/// # Examples
+///
+/// ```
+/// use lynx::parser::{OpTable, parse_pattern};
+///
+/// let ops = OpTable::default();
+/// for src in ["_", "Cons h t", "x : Int"] {
+///     assert_eq!(parse_pattern(src).unwrap().pretty(&ops).to_string(), src);
+/// }
+/// assert_eq!(parse_pattern("(x, y)").unwrap().to_string(), "(x, y)");
+/// assert!(parse_pattern("x y;").is_err());
+/// ```
+pub fn parse_pattern(src: &str) -> Result<Expr, Error> {
+    parse_single(src)
+}
+
+/// Lexes and parses Lynx source consisting of a single expression,
+/// rejecting anything after it.
+fn parse_single(src: &str) -> Result<Expr, Error> {
+    let tokens = tokenize(src)?;
+    let config = ParserConfig::default();
+    let mut diags = Diagnostics::new();
+    let mut parser = Parser::new(&tokens, &config, &mut diags);
+    let expr = parser.parse_expr(0)?;
+    if !parser.tokens.remaining().is_empty() {
+        parser.tokens.expect("end of input");
+        return Err(parser.tokens.unexpected());
+    }
+    Ok(expr)
+}
+
+/// Parses Lynx tokens like [`parse`], emitting warnings into `diags`.
+pub fn parse_with_diagnostics(
+    tokens: &[Token],
+    config: &ParserConfig,
+    diags: &mut Diagnostics,
+) -> Result<Vec<Expr>, Error> {
+    Parser::new(tokens, config, diags).parse_seq(None)
+}
+
+/// Maps the positions in the spans of `expr` through `positions`,
+/// returning [`None`] if any of them is missing.
+fn remap_spans(expr: &Expr, positions: &BTreeMap<Pos, Pos>) -> Option<Expr> {
+    let remap = |Span(start, end): Span| Some(Span(*positions.get(&start)?, *positions.get(&end)?));
+    let remap_all = |exprs: &[Expr]| {
+        exprs
+            .iter()
+            .map(|expr| remap_spans(expr, positions))
+            .collect::<Option<Vec<_>>>()
+    };
+
+    Some(match expr {
+        Expr::Atom(atom, span) => Expr::Atom(atom.clone(), remap(*span)?),
+        Expr::App(func, arg, span) => Expr::App(
+            Box::new(remap_spans(func, positions)?),
+            Box::new(remap_spans(arg, positions)?),
+            remap(*span)?,
+        ),
+        Expr::Tuple(exprs, span) => Expr::Tuple(remap_all(exprs)?, remap(*span)?),
+        Expr::List(exprs, span) => Expr::List(remap_all(exprs)?, remap(*span)?),
+        Expr::Block(exprs, span) => Expr::Block(remap_all(exprs)?, remap(*span)?),
+    })
+}
+
+/// Parses Lynx tokens like [`parse`] after an edit,
+/// given the top-level expressions `prev` parsed from the tokens `prev_tokens` before it.
+///
+/// Top-level expressions separated from the edit by an unchanged separator
+/// are reused, with their spans moved as needed,
+/// and only the ones in between are parsed again.
+/// The edit is found by comparing the tokens, so it need not be passed in.
+/// The result is the same as that of parsing `tokens` from scratch,
+/// which is what happens if any of the reparsed expressions fails to parse.
+pub fn reparse(
+    prev: &[Expr],
+    prev_tokens: &[Token],
+    tokens: &[Token],
+    config: &ParserConfig,
+) -> Result<Vec<Expr>, Error> {
+    // Tokens before the edit keep their positions
+    let common_prefix = prev_tokens
+        .iter()
+        .zip(tokens)
+        .take_while(|(prev, token)| prev.0 == token.0 && prev.1 == token.1)
+        .count();
+    // Tokens after the edit may move, which the parser does not care about
+    let common_suffix = prev_tokens
+        .iter()
+        .rev()
+        .zip(tokens.iter().rev())
+        .take(prev_tokens.len().min(tokens.len()) - common_prefix)
+        .take_while(|(prev, token)| prev.0 == token.0)
+        .count();
+    let suffix_start = prev_tokens.len() - common_suffix;
+
+    // Indices of the first and last tokens of each previous expression
+    let Some(ranges) = prev
+        .iter()
+        .map(|expr| {
+            let Span(start, end) = expr.span();
+            let first = prev_tokens
+                .binary_search_by_key(&start, |Token(_, span)| span.0)
+                .ok()?;
+            let last = prev_tokens
+                .binary_search_by_key(&end, |Token(_, span)| span.1)
+                .ok()?;
+            Some((first, last))
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return parse(tokens, config);
+    };
+
+    // An expression parses the same way if the separator ending it is unchanged,
+    // or if the one before it is, given that the expressions before it parse fine
+    let kept_prefix = ranges
+        .iter()
+        .take_while(|(_, last)| last + 1 < common_prefix)
+        .count();
+    let kept_suffix = ranges[kept_prefix..]
+        .iter()
+        .rev()
+        .take_while(|(first, _)| *first > suffix_start)
+        .count();
+
+    let reparse_start = ranges[..kept_prefix].last().map_or(0, |(_, last)| last + 1);
+    let prev_reparse_end = ranges[prev.len() - kept_suffix..]
+        .first()
+        .map_or(prev_tokens.len(), |(first, _)| *first);
+    let reparse_end = tokens.len() - (prev_tokens.len() - prev_reparse_end);
+    let Ok(reparsed) = parse(&tokens[reparse_start..reparse_end], config) else {
+        return parse(tokens, config);
+    };
+
+    // Positions of the tokens after the reparsed ones, before and after the edit
+    let positions: BTreeMap<_, _> = prev_tokens[prev_reparse_end..]
+        .iter()
+        .zip(&tokens[reparse_end..])
+        .flat_map(|(prev, token)| [(prev.1.0, token.1.0), (prev.1.1, token.1.1)])
+        .collect();
+    let Some(moved) = prev[prev.len() - kept_suffix..]
+        .iter()
+        .map(|expr| remap_spans(expr, &positions))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return parse(tokens, config);
+    };
+
+    Ok(prev[..kept_prefix]
+        .iter()
+        .cloned()
+        .chain(reparsed)
+        .chain(moved)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(src: &str) -> Result<Vec<Expr>, Error> {
+        parse(&tokenize(src).unwrap(), &ParserConfig::default())
+    }
+
+    fn display(src: &str) -> Vec<String> {
+        parse_str(src)
+            .unwrap()
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_empty_program() {
+        assert_eq!(display(""), Vec::<String>::new());
+        assert_eq!(display(";;"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_atoms() {
+        assert_eq!(
+            display(r#"(); 42; 3.5; 'c'; "s"; _; foo"#),
+            vec!["()", "42", "3.5", "'c'", "\"s\"", "_", "foo"]
+        );
+    }
+
+    #[test]
+    fn test_application_left_assoc() {
+        assert_eq!(display("f x y"), vec!["((f x) y)"]);
+    }
+
+    #[test]
+    fn test_application_over_infix() {
+        assert_eq!(display("Cons x xs ++ ys"), vec!["((++ ((Cons x) xs)) ys)"]);
+        assert_eq!(display("f x `div` g y"), vec!["((div (f x)) (g y))"]);
+        // Likewise in patterns
+        assert_eq!(
+            display("(Cons x xs | Nil) => x"),
+            vec!["((=> ((| ((Cons x) xs)) Nil)) x)"]
+        );
+        assert_eq!(
+            display("Cons x xs: List Int = l"),
+            vec!["((= ((: ((Cons x) xs)) (List Int))) l)"]
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(display("1 + 2 * 3"), vec!["((+ 1) ((* 2) 3))"]);
+        assert_eq!(display("(1 + 2) * 3"), vec!["((* ((+ 1) 2)) 3)"]);
+    }
+
+    #[test]
+    fn test_left_and_right_assoc() {
+        assert_eq!(display("a - b - c"), vec!["((- ((- a) b)) c)"]);
+        assert_eq!(display("x => y => x"), vec!["((=> x) ((=> y) x))"]);
+    }
+
+    #[test]
+    fn test_application_binds_tighter_than_infix() {
+        assert_eq!(display("f x + g y"), vec!["((+ (f x)) (g y))"]);
+    }
+
+    #[test]
+    fn test_access_binds_tighter_than_application() {
+        assert_eq!(display("m.f x"), vec!["(((. m) f) x)"]);
+        assert_eq!(display("std::math::pi"), vec!["((:: ((:: std) math)) pi)"]);
+    }
+
+    #[test]
+    fn test_prefix_ops() {
+        assert_eq!(display("-a * b"), vec!["((* (negate a)) b)"]);
+        assert_eq!(display("println !r"), vec!["(println (! r))"]);
+        assert_eq!(display("f -x"), vec!["((- f) x)"]);
+    }
+
+    #[test]
+    fn test_negation_in_elements() {
+        assert_eq!(
+            display("[-1, -2, 3 - 4]"),
+            vec!["[(negate 1), (negate 2), ((- 3) 4)]"]
+        );
+        assert_eq!(display("[-1, -2]"), vec!["[(negate 1), (negate 2)]"]);
+        assert_eq!(display("[3 - 4]"), vec!["[((- 3) 4)]"]);
+        assert_eq!(display("(a, -b)"), vec!["(a, (negate b))"]);
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_eq!(display("a, b, c"), vec!["(a, b, c)"]);
+        assert_eq!(display("(x, _) = p"), vec!["((= (x, _)) p)"]);
+    }
+
+    #[test]
+    fn test_lambda_patterns() {
+        assert_eq!(display("(x, y) => x"), vec!["((=> (x, y)) x)"]);
+        assert_eq!(display("(cons h _) => h"), vec!["((=> ((cons h) _)) h)"]);
+        assert_eq!(
+            display("((a, b), c: Int) => a"),
+            vec!["((=> ((a, b), ((: c) Int))) a)"]
+        );
+    }
+
+    #[test]
+    fn test_unit_and_grouping() {
+        assert_eq!(display("()"), vec!["()"]);
+        assert_eq!(display("(x); ((x))"), vec!["x", "x"]);
+        assert_eq!(display("(x, y)"), vec!["(x, y)"]);
+        assert_eq!(display("((x, y))"), vec!["(x, y)"]);
+    }
+
+    #[test]
+    fn test_unary_tuple_error() {
+        let result = parse_str("x,;");
+        assert!(matches!(result, Err(Error(ExpectedExpr, _))));
+        let result = parse_str("[x,]");
+        assert!(matches!(result, Err(Error(ExpectedExpr, _))));
+    }
+
+    #[test]
+    fn test_tuple_section() {
+        assert_eq!(display("(, x)"), vec!["((=> #0) (#0, x))"]);
+        assert_eq!(display("(x ,)"), vec!["((=> #0) (x, #0))"]);
+        assert_eq!(display("(a, , c)"), vec!["((=> #0) (a, #0, c))"]);
+        assert_eq!(
+            display("(, f x,)"),
+            vec!["((=> #0) ((=> #1) (#0, (f x), #1)))"]
+        );
+        // Nested brackets are not searched for holes
+        assert_eq!(display("(f (x, y))"), vec!["(f (x, y))"]);
+        assert_eq!(
+            display("([x, y], (, z))"),
+            vec!["([x, y], ((=> #0) (#0, z)))"]
+        );
+        // Nor are nested blocks, `;` within them notwithstanding
+        assert_eq!(display("({a; b},)"), vec!["((=> #0) ({a; b; }, #0))"]);
+        assert_eq!(display("(x, {a; b},)"), vec!["((=> #0) (x, {a; b; }, #0))"]);
+
+        let exprs = parse_str("(a, , c)").unwrap();
+        let (_, args) = exprs[0].as_application();
+        assert_eq!(args[0].span(), Span(Pos(1, 5), Pos(1, 5)));
+        assert_eq!(exprs[0].span(), Span(Pos(1, 1), Pos(1, 8)));
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(display("[]"), vec!["[]"]);
+        assert_eq!(display("[1, 2 + 3]"), vec!["[1, ((+ 2) 3)]"]);
+        assert_eq!(display("[(1, 2)]"), vec!["[(1, 2)]"]);
+    }
+
+    #[test]
+    fn test_block() {
+        assert_eq!(
+            display("if (c) {a = 1; a} else {2;}"),
+            vec!["((((if c) {((= a) 1); a; }) else) {2; })"]
+        );
+    }
+
+    #[test]
+    fn test_nullary_constructors() {
+        // Constructors are ordinary names, applied to as many arguments as given
+        assert_eq!(parse_pattern("Nil").unwrap().to_string(), "Nil");
+        assert_eq!(display("Nil; Cons x Nil"), vec!["Nil", "((Cons x) Nil)"]);
+        assert_eq!(
+            display("match xs {Nil => 0; Cons x xs => x;}"),
+            vec!["((match xs) {((=> Nil) 0); ((=> ((Cons x) xs)) x); })"]
+        );
+        assert_eq!(
+            display("data (List (A: Type)) {Nil; Cons @A (_: A, _: List A);}"),
+            vec![
+                "((data (List ((: A) Type))) \
+                 {Nil; ((Cons (@ A)) (((: _) A), ((: _) (List A)))); })"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macro_call_as_arg() {
+        assert_eq!(
+            display("f (if (c) {a} else {b})"),
+            vec!["(f ((((if c) {a; }) else) {b; }))"]
+        );
+        // Macro names are ordinary names, so they do not start a new argument
+        assert_eq!(
+            display("f if (c) {a} else {b}"),
+            vec!["(((((f if) c) {a; }) else) {b; })"]
+        );
+    }
+
+    #[test]
+    fn test_blank_line_sep() {
+        assert_eq!(display("f\n\nx\n\n\ny = 1;"), vec!["f", "x", "((= y) 1)"]);
+    }
+
+    #[test]
+    fn test_semicolon_sep() {
+        let config = ParserConfig {
+            sep: SepPolicy::Semicolon,
+            ..Default::default()
+        };
+        let exprs = parse(&tokenize("f\n\nx\n\n\ny = 1;").unwrap(), &config).unwrap();
+        let exprs: Vec<String> = exprs.iter().map(|expr| expr.to_string()).collect();
+        assert_eq!(exprs, vec!["((= ((f x) y)) 1)"]);
+    }
+
+    #[test]
+    fn test_blank_line_ends_expr() {
+        let result = parse_str("x =\n\n1;");
+        assert!(matches!(result, Err(Error(ExpectedExpr, _))));
+    }
+
+    #[test]
+    fn test_blank_line_in_brackets() {
+        assert_eq!(
+            display("xs = [1,\n\n  2,\n  3]\n\ny"),
+            vec!["((= xs) [1, 2, 3])", "y"]
+        );
+        assert_eq!(display("f (a,\n\nb)"), vec!["(f (a, b))"]);
+        // Blocks inside brackets are still separated by blank lines
+        assert_eq!(display("[{a\n\nb}]"), vec!["[{a; b; }]"]);
+    }
+
+    #[test]
+    fn test_remaining() {
+        let tokens = tokenize("f (x)\n\ny").unwrap();
+        let mut stream = TokenStream::new(&tokens, true);
+        assert_eq!(stream.remaining().len(), tokens.len());
+        stream.next();
+        stream.next();
+        assert!(matches!(stream.remaining(), [Token(Name(name), _), ..] if name == "x"));
+        assert_eq!(stream.remaining().len(), tokens.len() - 2);
+        // Insignificant tokens before the lookahead are skipped
+        stream.next();
+        stream.next();
+        assert!(matches!(stream.remaining(), [Token(Name(name), _)] if name == "y"));
+        stream.next();
+        assert!(stream.remaining().is_empty());
+    }
+
+    #[test]
+    fn test_expect_one_of() {
+        let tokens = tokenize("x 1 ;").unwrap();
+        let mut stream = TokenStream::new(&tokens, false);
+        let kinds = [Name(String::new()), IntLit(0)];
+        assert!(matches!(stream.expect_one_of(&kinds), Ok(Token(Name(name), _)) if name == "x"));
+        assert!(matches!(
+            stream.expect_one_of(&kinds),
+            Ok(Token(IntLit(1), _))
+        ));
+
+        stream.expect("name");
+        let result = stream.expect_one_of(&kinds);
+        let Err(Error(UnexpectedToken(found, expected), _)) = result else {
+            panic!("expected unexpected token");
+        };
+        assert_eq!(found, "`;`");
+        assert_eq!(expected, vec!["name"]);
+        // Nothing is consumed on failure
+        assert!(matches!(stream.peek(), Some(Token(Semicolon, _))));
+    }
+
+    #[test]
+    fn test_op_as_name() {
+        assert_eq!(display("reduce (+)"), vec!["(reduce +)"]);
+    }
+
+    #[test]
+    fn test_adjacent_str_lits() {
+        assert_eq!(display("\"a\" \\\\b\n\\\\c"), vec!["\"a\\nb\\nc\""]);
+    }
+
+    #[test]
+    fn test_spans() {
+        let exprs = parse_str("x = f 1;\n  {y}").unwrap();
+        let Span(Pos(l0, c0), Pos(l1, c1)) = exprs[0].span();
+        assert_eq!((l0, c0, l1, c1), (1, 1, 1, 7));
+        let Span(Pos(l0, c0), Pos(l1, c1)) = exprs[1].span();
+        assert_eq!((l0, c0, l1, c1), (2, 3, 2, 5));
+    }
+
+    #[test]
+    fn test_redundant_semicolon_warning() {
+        let tokens = tokenize("; a;; b;\n\n;{c;}").unwrap();
+        let mut diags = Diagnostics::new();
+        let exprs = parse_with_diagnostics(&tokens, &ParserConfig::default(), &mut diags).unwrap();
+        assert_eq!(exprs.len(), 3);
+        assert!(!diags.has_errors());
+        let cols: Vec<_> = diags
+            .into_vec()
+            .into_iter()
+            .map(|Error(kind, Span(Pos(line_no, col_no), _))| {
+                assert!(matches!(kind, RedundantSemicolon));
+                (line_no, col_no)
+            })
+            .collect();
+        assert_eq!(cols, vec![(1, 1), (1, 5), (3, 1)]);
+    }
+
+    #[test]
+    fn test_deprecated_op_warning() {
+        let mut config = ParserConfig::default();
+        config.ops.deprecate("/=", "!=");
+        let tokens = tokenize("a /= b && c /= d").unwrap();
+        let mut diags = Diagnostics::new();
+        let exprs = parse_with_diagnostics(&tokens, &config, &mut diags).unwrap();
+        assert_eq!(exprs[0].to_string(), "((&& ((!= a) b)) ((!= c) d))");
+
+        assert!(!diags.has_errors());
+        let diags: Vec<_> = diags
+            .into_vec()
+            .into_iter()
+            .map(|Error(kind, Span(Pos(_, col_no), _))| (col_no, kind.to_string()))
+            .collect();
+        let warning = "deprecated syntax; write `!=` instead".to_string();
+        assert_eq!(diags, vec![(3, warning.clone()), (13, warning)]);
+    }
+
+    #[test]
+    fn test_parse_module() {
+        let src = "\n  a = 1;\n\n  f a;  -- done\n\n";
+        let Module(exprs, span) =
+            parse_module(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        assert_eq!(exprs.len(), 2);
+        assert_eq!(span, Span(Pos(2, 3), Pos(4, 6)));
+        assert_eq!(span.text(src), "a = 1;\n\n  f a;");
+
+        let Module(exprs, span) =
+            parse_module(&tokenize("-- nothing").unwrap(), &ParserConfig::default()).unwrap();
+        assert!(exprs.is_empty());
+        assert!(span.is_dummy());
+    }
+
+    #[test]
+    fn test_parse_source() {
+        let (module, diags) = parse_source("a = 1;\n\nf a");
+        assert_eq!(module.unwrap().0.len(), 2);
+        assert!(diags.is_empty());
+
+        // Inputs that must fail cleanly rather than panic
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}x{}", open.repeat(depth), close.repeat(depth))
+        };
+        let srcs = [
+            ")".to_string(),
+            "x \\".to_string(),
+            "\\".to_string(),
+            "(".to_string(),
+            "`".to_string(),
+            nested("(", ")", 10_000),
+            nested("[", "]", 10_000),
+            nested("{", "}", 10_000),
+            nested("f (", ")", 10_000),
+            nested("- ", "", 10_000),
+            format!("f {}x", "! ".repeat(10_000)),
+            nested("x = ", "", 10_000),
+            "0x1.8p-2147483648".to_string(),
+            "1e999".to_string(),
+            // Long chains build deep trees without nesting
+            "1 + ".repeat(50_000) + "1",
+            "1 + ".repeat(50_000) + ")",
+            "f".to_string() + &" x".repeat(300_000),
+            "m".to_string() + &".f".repeat(100_000),
+            format!("({})", ", ".repeat(100_000)),
+        ];
+        for src in &srcs {
+            let (module, diags) = parse_source(src);
+            assert!(module.is_none(), "parsing {:?}", src);
+            assert!(!diags.is_empty(), "parsing {:?}", src);
+        }
+        for src in [&srcs[5], &srcs[14], &srcs[16]] {
+            let (_, diags) = parse_source(src);
+            assert!(matches!(diags[0], Error(NestingTooDeep, _)));
+        }
+
+        // Nesting up to the limit is fine
+        for src in [
+            nested("(", ")", MAX_NESTING - 1),
+            nested("- ", "", MAX_NESTING - 1),
+            nested("x = ", "", MAX_NESTING - 1),
+            // `1 + 1` is 3 deep, each further `+ 1` adding 2
+            "1 + ".repeat((MAX_DEPTH - 1) / 2) + "1",
+            "f".to_string() + &" x".repeat(MAX_DEPTH - 1),
+        ] {
+            let (module, diags) = parse_source(&src);
+            assert!(module.is_some(), "parsing {:?}: {:?}", src, diags);
+        }
+        let (_, diags) = parse_source(&("f".to_string() + &" x".repeat(MAX_DEPTH)));
+        assert!(matches!(diags[0], Error(NestingTooDeep, _)));
+    }
+
+    #[test]
+    fn test_parse_type() {
+        assert_eq!(
+            parse_type("(a -> b) -> List a").unwrap().to_string(),
+            "((-> ((-> a) b)) (List a))"
+        );
+        let Err(Error(UnexpectedToken(found, expected), _)) = parse_type("a -> b; c") else {
+            panic!("expected unexpected token");
+        };
+        assert_eq!(found, "`;`");
+        assert_eq!(expected, vec!["argument", "end of input", "operator"]);
+        assert!(matches!(parse_type(""), Err(Error(UnexpectedEof(_), _))));
+    }
+
+    #[test]
+    fn test_type_hole() {
+        // `_` stands for a type to be inferred, anywhere in a type
+        let ty = parse_type("_").unwrap();
+        assert!(
+            matches!(ty, Expr::Atom(AtomKind::Wildcard, span) if span == Span::point(Pos(1, 1)))
+        );
+        let ty = parse_type("_ -> Int").unwrap();
+        let (head, args) = ty.as_application();
+        assert_eq!(head.to_string(), "->");
+        assert!(matches!(args[0], Expr::Atom(AtomKind::Wildcard, _)));
+        assert_eq!(display("f: _ -> Int"), vec!["((: f) ((-> _) Int))"]);
+        assert_eq!(display("x: List _ = []"), vec!["((= ((: x) (List _))) [])"]);
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        let ops = OpTable::default();
+        for src in ["_", "(x, y)", "Cons h t", "(Cons h _, [x]: List Int)"] {
+            let pattern = parse_pattern(src).unwrap();
+            let printed = pattern.pretty(&ops).to_string();
+            let reparsed = parse_pattern(&printed).unwrap();
+            assert_eq!(reparsed.to_string(), pattern.to_string());
+        }
+        assert_eq!(
+            parse_pattern("Cons h t").unwrap().to_string(),
+            "((Cons h) t)"
+        );
+        assert!(matches!(
+            parse_pattern("x = 1; y"),
+            Err(Error(UnexpectedToken(..), _))
+        ));
+    }
+
+    /// Asserts that reparsing `src` after editing `prev_src`
+    /// gives the same result as parsing it from scratch.
+    fn assert_reparse(prev_src: &str, src: &str) {
+        let config = ParserConfig::default();
+        let prev_tokens = tokenize(prev_src).unwrap();
+        let prev = parse(&prev_tokens, &config).unwrap();
+        let tokens = tokenize(src).unwrap();
+        assert_eq!(
+            format!("{:?}", reparse(&prev, &prev_tokens, &tokens, &config)),
+            format!("{:?}", parse(&tokens, &config)),
+        );
+    }
+
+    #[test]
+    fn test_reparse() {
+        let prev_src = "a = f x;
+b = [1, 2]
+
+c = (y => y) b;";
+        // Edit in the middle declaration
+        assert_reparse(
+            prev_src,
+            "a = f x;
+b = [1, 2 + 3]
+
+c = (y => y) b;",
+        );
+        // Edits that move the declarations after them
+        assert_reparse(
+            prev_src,
+            "a = f x;
+b = [
+  1,
+  2,
+]
+
+c = (y => y) b;",
+        );
+        assert_reparse(
+            prev_src,
+            "a = f (g
+  x);
+b = [1, 2]
+
+c = (y => y) b;",
+        );
+        assert_reparse(prev_src, "a = f x; b = [1, 2]; c = (y => y) b;");
+        // Edits to separators
+        assert_reparse(
+            prev_src,
+            "a = f x
+b = [1, 2]
+
+c = (y => y) b;",
+        );
+        assert_reparse(
+            prev_src,
+            "a = f x;
+b = [1, 2]
+c = (y => y) b;",
+        );
+        assert_reparse(
+            prev_src,
+            "a = f x;
+b = [1, 2];
+d;
+
+c = (y => y) b;",
+        );
+        // Edits that fail to parse
+        assert_reparse(
+            prev_src,
+            "a = f x;
+b = [1, 2
+
+c = (y => y) b;",
+        );
+        assert_reparse(
+            prev_src,
+            "a = f x;
+b = (1, 2]
+
+c = (y => y) b;",
+        );
+    }
+
+    #[test]
+    fn test_reparse_reuses_exprs() {
+        let config = ParserConfig::default();
+        let prev_tokens = tokenize("a = 1;\nb = 2;\nc = 3;").unwrap();
+        // Stands in for what was parsed from `prev_tokens`, to tell reused expressions apart
+        let prev = parse_str("x = 1;\ny = 2;\nz = 3;").unwrap();
+        let tokens = tokenize("a = 1;\nb = 2 + 2;\n  c = 3;").unwrap();
+        let exprs = reparse(&prev, &prev_tokens, &tokens, &config).unwrap();
+        let Span(Pos(line, col), _) = exprs[2].span();
+        assert_eq!((line, col), (3, 3));
+        let exprs: Vec<_> = exprs.iter().map(|expr| expr.to_string()).collect();
+        assert_eq!(exprs, vec!["((= x) 1)", "((= b) ((+ 2) 2))", "((= z) 3)"]);
+    }
+
+    #[test]
+    fn test_missing_rp_error() {
+        let result = parse_str("(a + b");
+        let Err(Error(UnexpectedEof(expected), _)) = result else {
+            panic!("expected unexpected end of input");
+        };
+        assert_eq!(expected, vec!["`)`", "argument", "operator"]);
+    }
+
+    #[test]
+    fn test_unexpected_token_error() {
+        let result = parse_str("(a b]");
+        let Err(Error(UnexpectedToken(found, expected), _)) = result else {
+            panic!("expected unexpected token");
+        };
+        assert_eq!(found, "`]`");
+        assert_eq!(expected, vec!["`)`", "argument", "operator"]);
+    }
+
+    #[test]
+    fn test_missing_separator_error() {
+        let result = parse_str("{a = 1 )");
+        let Err(Error(err @ UnexpectedToken(..), _)) = result else {
+            panic!("expected unexpected token");
+        };
+        assert_eq!(
+            err.to_string(),
+            "expected one of `;`, `}`, argument, blank line, operator, found `)`"
+        );
+
+        let config = ParserConfig {
+            sep: SepPolicy::Semicolon,
+            ..ParserConfig::default()
+        };
+        let result = parse(&tokenize("a)").unwrap(), &config);
+        let Err(Error(UnexpectedToken(_, expected), _)) = result else {
+            panic!("expected unexpected token");
+        };
+        assert_eq!(expected, vec!["`;`", "argument", "operator"]);
+    }
+
+    #[test]
+    fn test_expected_expr_error() {
+        let result = parse_str("a + ;");
+        assert!(matches!(result, Err(Error(ExpectedExpr, _))));
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(display("a < b"), vec!["((< a) b)"]);
+        assert_eq!(
+            display("a + 1 == b && c"),
+            vec!["((&& ((== ((+ a) 1)) b)) c)"]
+        );
+        assert_eq!(display("(a < b) == c"), vec!["((== ((< a) b)) c)"]);
+    }
+
+    #[test]
+    fn test_chained_comparison_error() {
+        let result = parse_str("x = a < b < c");
+        let Err(Error(ChainedComparison(hint), Span(Pos(1, c0), Pos(1, c1)))) = result else {
+            panic!("expected chained comparison error");
+        };
+        assert_eq!(hint, "a < b && b < c");
+        assert_eq!((c0, c1), (5, 13));
+
+        let result = parse_str("f a <= b + 1 == (c, d)");
+        let Err(Error(ChainedComparison(hint), _)) = result else {
+            panic!("expected chained comparison error");
+        };
+        assert_eq!(hint, "f a <= b + 1 && b + 1 == (c, d)");
+    }
+
+    #[test]
+    fn test_backtick_name() {
+        assert_eq!(display("a `mod` b"), vec!["((mod a) b)"]);
+        assert_eq!(
+            display("f a `div` b `mod` c + 1"),
+            vec!["((+ ((mod ((div (f a)) b)) c)) 1)"]
+        );
+        let result = parse_str("`div` b");
+        assert!(matches!(result, Err(Error(ExpectedExpr, _))));
+    }
+
+    #[test]
+    fn test_dot_spacing() {
+        // `.` is access however it is spaced, never composition
+        assert_eq!(display("M.x"), vec!["((. M) x)"]);
+        assert_eq!(display("f . g"), display("f.g"));
+        assert_eq!(display("f . g x"), vec!["(((. f) g) x)"]);
+        // Number literals keep their `.`
+        assert_eq!(display("1.5; m.f 1.5"), vec!["1.5", "(((. m) f) 1.5)"]);
+    }
+
+    #[test]
+    fn test_equals_runs() {
+        assert_eq!(display("a=b"), vec!["((= a) b)"]);
+        assert_eq!(display("x=>x==y"), vec!["((=> x) ((== x) y))"]);
+        // Not `=>` followed by `>`, but an operator of its own with the default fixity
+        assert_eq!(display("a==>b || c"), vec!["((|| ((==> a) b)) c)"]);
+    }
+
+    #[test]
+    fn test_qualified_backtick_name() {
+        assert_eq!(display("a `M.div` b"), vec!["((((. M) div) a) b)"]);
+        assert_eq!(display("a `M.N.f` b"), vec!["((((. ((. M) N)) f) a) b)"]);
+        let exprs = parse_str("a `M.div` b").unwrap();
+        let Expr::App(lhs, _, _) = &exprs[0] else {
+            panic!("expected application");
+        };
+        let Expr::App(op, _, _) = lhs.as_ref() else {
+            panic!("expected application");
+        };
+        assert_eq!(op.span(), Span(Pos(1, 4), Pos(1, 8)));
+        let (dot, args) = op.as_application();
+        let spans: Vec<_> = std::iter::once(dot).chain(args).map(Expr::span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::point(Pos(1, 5)),
+                Span::point(Pos(1, 4)),
+                Span(Pos(1, 6), Pos(1, 8))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_fixity() {
+        assert_eq!(display("a <+> b <+> c"), vec!["((<+> ((<+> a) b)) c)"]);
+        assert_eq!(display("a <+> f b * c"), vec!["((* ((<+> a) (f b))) c)"]);
+        assert_eq!(display("a <+> b `div` c"), vec!["((div ((<+> a) b)) c)"]);
+        // Declared fixities override the default
+        assert_eq!(display("a -> b -> c"), vec!["((-> a) ((-> b) c))"]);
+        assert_eq!(display("a : b <+> c"), vec!["((: a) ((<+> b) c))"]);
+    }
+}
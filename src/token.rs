@@ -1,7 +1,10 @@
 use std::fmt;
 
 /// Position of a character in Lynx source.
-#[derive(Debug, Clone, Copy)]
+///
+/// Positions are ordered line-major, then by column.
+/// The default position `0:0` lies outside any source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Pos(
     /// Line number, `1`-based.
     pub usize,
@@ -9,6 +12,27 @@ pub struct Pos(
     pub usize,
 );
 
+impl Pos {
+    /// Returns the byte offset of the position in `src`,
+    /// clamped to the end of its line, or to the end of `src`.
+    pub fn byte_offset(&self, src: &str) -> usize {
+        let Pos(line_no, col_no) = *self;
+        let mut line_start = 0;
+        for (line_idx, line) in src.split_inclusive('\n').enumerate() {
+            if line_idx + 1 == line_no {
+                let line = line.trim_end_matches(['\n', '\r']);
+                let offset = line
+                    .char_indices()
+                    .nth(col_no.saturating_sub(1))
+                    .map_or(line.len(), |(offset, _)| offset);
+                return line_start + offset;
+            }
+            line_start += line.len();
+        }
+        src.len()
+    }
+}
+
 impl fmt::Display for Pos {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}:{}", self.0, self.1)
@@ -16,7 +40,10 @@ impl fmt::Display for Pos {
 }
 
 /// Position of a span of text in Lynx source.
-#[derive(Debug)]
+///
+/// Spans are ordered by their starting positions, then by their end positions.
+/// The default span is [`Span::dummy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Span(
     /// Starting position.
     pub Pos,
@@ -24,6 +51,54 @@ pub struct Span(
     pub Pos,
 );
 
+impl Span {
+    /// Creates a span at a single position,
+    /// e.g. where something is missing.
+    pub fn point(pos: Pos) -> Span {
+        Span(pos, pos)
+    }
+
+    /// Creates a span at `0:0`, which lies outside any source,
+    /// for synthesized nodes without a source location.
+    pub fn dummy() -> Span {
+        Span::default()
+    }
+
+    /// Checks if the span is [`Span::dummy`].
+    pub fn is_dummy(&self) -> bool {
+        *self == Span::dummy()
+    }
+
+    /// Checks if the span covers all of `other`, which includes being equal to it.
+    pub fn encloses(&self, other: &Span) -> bool {
+        self.0 <= other.0 && other.1 <= self.1
+    }
+
+    /// Joins spans into the smallest one covering all of them,
+    /// returning [`None`] if there are none.
+    pub fn join_all(spans: impl IntoIterator<Item = Span>) -> Option<Span> {
+        spans
+            .into_iter()
+            .reduce(|Span(start, end), Span(next_start, next_end)| {
+                Span(start.min(next_start), end.max(next_end))
+            })
+    }
+
+    /// Returns the slice of `src` the span covers, as written.
+    pub fn text<'s>(&self, src: &'s str) -> &'s str {
+        let start = self.0.byte_offset(src);
+        let end = self.1.byte_offset(src);
+        // The end position is inclusive
+        let end = end
+            + src[end..]
+                .chars()
+                .next()
+                .filter(|c| !matches!(c, '\n' | '\r'))
+                .map_or(0, char::len_utf8);
+        &src[start..end.max(start)]
+    }
+}
+
 impl fmt::Display for Span {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[{}, {}]", self.0, self.1)
@@ -46,6 +121,8 @@ pub enum TokenKind {
 
     /// Alphabetic/symbolic name.
     Name(String),
+    /// Alphabetic name in backticks, used as an infix operator, e.g. `` `div` ``.
+    BacktickName(String),
 
     /// `(` (left parenthesis).
     Lp,
@@ -61,6 +138,60 @@ pub enum TokenKind {
     Rc,
     /// `;`.
     Semicolon,
+    /// Blank line, i.e. one or more lines containing only whitespace.
+    BlankLine,
+}
+
+impl TokenKind {
+    /// Returns the name of the kind, regardless of the value it carries,
+    /// e.g. `"IntLit"` for both `IntLit(1)` and `IntLit(2)`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::UnitLit => "UnitLit",
+            TokenKind::IntLit(_) => "IntLit",
+            TokenKind::FloatLit(_) => "FloatLit",
+            TokenKind::CharLit(_) => "CharLit",
+            TokenKind::StrLit(_) => "StrLit",
+            TokenKind::Name(_) => "Name",
+            TokenKind::BacktickName(_) => "BacktickName",
+            TokenKind::Lp => "Lp",
+            TokenKind::Rp => "Rp",
+            TokenKind::Lb => "Lb",
+            TokenKind::Rb => "Rb",
+            TokenKind::Lc => "Lc",
+            TokenKind::Rc => "Rc",
+            TokenKind::Semicolon => "Semicolon",
+            TokenKind::BlankLine => "BlankLine",
+        }
+    }
+
+    /// Checks if two kinds are the same, regardless of the values they carry,
+    /// e.g. `IntLit(1)` and `IntLit(2)`.
+    pub fn same_kind(&self, other: &TokenKind) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::UnitLit => write!(f, "`()`"),
+            TokenKind::IntLit(value) => write!(f, "`{}`", value),
+            TokenKind::FloatLit(value) => write!(f, "`{:?}`", value),
+            TokenKind::CharLit(value) => write!(f, "`{:?}`", value),
+            TokenKind::StrLit(_) => write!(f, "string literal"),
+            TokenKind::Name(name) => write!(f, "`{}`", name),
+            TokenKind::BacktickName(name) => write!(f, "`` `{}` ``", name),
+            TokenKind::Lp => write!(f, "`(`"),
+            TokenKind::Rp => write!(f, "`)`"),
+            TokenKind::Lb => write!(f, "`[`"),
+            TokenKind::Rb => write!(f, "`]`"),
+            TokenKind::Lc => write!(f, "`{{`"),
+            TokenKind::Rc => write!(f, "`}}`"),
+            TokenKind::Semicolon => write!(f, "`;`"),
+            TokenKind::BlankLine => write!(f, "blank line"),
+        }
+    }
 }
 
 /// Token of Lynx source.
@@ -72,8 +203,171 @@ pub struct Token(
     pub Span,
 );
 
+impl Token {
+    /// Returns the slice of `src` the token was lexed from,
+    /// which is empty for [`TokenKind::BlankLine`].
+    pub fn text<'s>(&self, src: &'s str) -> &'s str {
+        match self.0 {
+            TokenKind::BlankLine => "",
+            _ => self.1.text(src),
+        }
+    }
+
+    /// Returns the source spelling of the token without the source at hand,
+    /// which is the text it was lexed from for names and punctuation,
+    /// and a canonical spelling of the value for literals,
+    /// e.g. `31` for `0x1F`.
+    pub fn spelling(&self) -> String {
+        match &self.0 {
+            TokenKind::UnitLit => "()".to_string(),
+            TokenKind::IntLit(value) => value.to_string(),
+            TokenKind::FloatLit(value) => format!("{:?}", value),
+            TokenKind::CharLit(value) => format!("{:?}", value),
+            TokenKind::StrLit(value) => format!("{:?}", value),
+            TokenKind::Name(name) => name.clone(),
+            TokenKind::BacktickName(name) => format!("`{}`", name),
+            TokenKind::Lp => "(".to_string(),
+            TokenKind::Rp => ")".to_string(),
+            TokenKind::Lb => "[".to_string(),
+            TokenKind::Rb => "]".to_string(),
+            TokenKind::Lc => "{".to_string(),
+            TokenKind::Rc => "}".to_string(),
+            TokenKind::Semicolon => ";".to_string(),
+            TokenKind::BlankLine => String::new(),
+        }
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}@{}", self.0, self.1)
     }
 }
+
+/// Comment in Lynx source, kept as trivia for tooling such as formatters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment(
+    /// Text of the comment as written, starting with `--`.
+    pub String,
+    /// Position in the source.
+    pub Span,
+);
+
+/// Comments attached to a [`Token`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trivia {
+    /// Comments on lines of their own before the token.
+    pub leading: Vec<Comment>,
+    /// Comment after the token on the same line.
+    pub trailing: Option<Comment>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(span: Span) -> (usize, usize, usize, usize) {
+        let Span(Pos(l0, c0), Pos(l1, c1)) = span;
+        (l0, c0, l1, c1)
+    }
+
+    #[test]
+    fn test_join_all() {
+        let spans = [
+            Span(Pos(1, 5), Pos(1, 7)),
+            Span(Pos(1, 1), Pos(1, 3)),
+            Span(Pos(2, 1), Pos(2, 4)),
+        ];
+        let span = Span::join_all(spans).unwrap();
+        assert_eq!(bounds(span), (1, 1, 2, 4));
+
+        let span = Span::join_all([Span(Pos(3, 2), Pos(3, 2))]).unwrap();
+        assert_eq!(bounds(span), (3, 2, 3, 2));
+    }
+
+    #[test]
+    fn test_pos_ord() {
+        assert!(Pos(1, 9) < Pos(2, 1));
+        assert!(Pos(2, 1) < Pos(2, 3));
+        assert_eq!(Pos(3, 4).cmp(&Pos(3, 4)), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn test_span_ord() {
+        let mut spans = vec![
+            Span(Pos(2, 1), Pos(2, 2)),
+            Span(Pos(1, 5), Pos(1, 9)),
+            Span(Pos(1, 5), Pos(1, 6)),
+        ];
+        spans.sort();
+        let spans: Vec<_> = spans.into_iter().map(bounds).collect();
+        assert_eq!(spans, vec![(1, 5, 1, 6), (1, 5, 1, 9), (2, 1, 2, 2)]);
+    }
+
+    #[test]
+    fn test_byte_offset() {
+        let src = "ab\r\nλx\n";
+        assert_eq!(Pos(1, 2).byte_offset(src), 1);
+        assert_eq!(Pos(2, 1).byte_offset(src), 4);
+        assert_eq!(Pos(2, 2).byte_offset(src), 6);
+        // Clamped to the end of the line, then of the source
+        assert_eq!(Pos(1, 9).byte_offset(src), 2);
+        assert_eq!(Pos(9, 1).byte_offset(src), src.len());
+    }
+
+    #[test]
+    fn test_token_text() {
+        let src = "f 0x1F\n\n  \"λ\" 1_0.5e1\n";
+        let tokens = crate::lexer::tokenize(src).unwrap();
+        let texts: Vec<_> = tokens.iter().map(|token| token.text(src)).collect();
+        assert_eq!(texts, vec!["f", "0x1F", "", "\"λ\"", "1_0.5e1"]);
+    }
+
+    #[test]
+    fn test_token_spelling() {
+        let src = "f `div` (->>) [1, 2.5];\n\n{'\\n' \"a\\\"λ\"} ()";
+        let tokens = crate::lexer::tokenize(src).unwrap();
+        for token in &tokens {
+            assert_eq!(token.spelling(), token.text(src));
+        }
+
+        // Literals are spelled canonically
+        let src = "0x1F 1_0.5e1 '\\u{41}'";
+        let tokens = crate::lexer::tokenize(src).unwrap();
+        let spellings: Vec<_> = tokens.iter().map(Token::spelling).collect();
+        assert_eq!(spellings, vec!["31", "105.0", "'A'"]);
+    }
+
+    #[test]
+    fn test_same_kind() {
+        assert!(TokenKind::IntLit(1).same_kind(&TokenKind::IntLit(2)));
+        assert!(TokenKind::Name("a".to_string()).same_kind(&TokenKind::Name("+".to_string())));
+        assert!(
+            !TokenKind::Name("a".to_string()).same_kind(&TokenKind::BacktickName("a".to_string()))
+        );
+        assert!(!TokenKind::Lp.same_kind(&TokenKind::Rp));
+    }
+
+    #[test]
+    fn test_point() {
+        let span = Span::point(Pos(2, 7));
+        assert_eq!(bounds(span), (2, 7, 2, 7));
+        assert_eq!(span.text("a\nbcdefgh\n"), "h");
+        assert_eq!(Span::point(Pos(1, 2)).text("a\n"), "");
+    }
+
+    #[test]
+    fn test_encloses() {
+        let span = Span(Pos(1, 3), Pos(2, 4));
+        assert!(span.encloses(&span));
+        assert!(span.encloses(&Span(Pos(1, 9), Pos(2, 1))));
+        assert!(span.encloses(&Span::point(Pos(2, 4))));
+        assert!(!span.encloses(&Span(Pos(1, 2), Pos(1, 5))));
+        assert!(!span.encloses(&Span::point(Pos(2, 5))));
+    }
+
+    #[test]
+    fn test_join_all_empty() {
+        assert!(Span::join_all([]).is_none());
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::{AtomKind, Expr},
+    error::{Diagnostics, Error, ErrorKind::*},
+    eval::split_guard,
+    token::Span,
+};
+
+/// Table of the data types declared with the `data` macro.
+///
+/// This type is an internal helper for [`validate`]
+/// and is *not* intended for public use.
+#[derive(Default)]
+struct DataTable {
+    /// Constructors of each data type, in order of declaration.
+    types: HashMap<String, Vec<String>>,
+
+    /// Data type of each constructor.
+    ctors: HashMap<String, String>,
+}
+
+impl DataTable {
+    /// Collects the data types declared anywhere in `exprs`,
+    /// e.g. `List` with `Nil` and `Cons` for `data (List (A: Type)) {Nil; Cons ...}`.
+    fn collect(exprs: &[Expr]) -> Self {
+        let mut data = Self::default();
+        for expr in exprs {
+            data.collect_expr(expr);
+        }
+        data
+    }
+
+    /// Collects the data types declared in a single expression and its subexpressions.
+    fn collect_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Atom(..) => {}
+            Expr::App(func, arg, _) => {
+                let (head, args) = expr.as_application();
+                if let (Expr::Atom(AtomKind::Name(name), _), [decl, Expr::Block(items, _)]) =
+                    (head, args.as_slice())
+                    && name == "data"
+                    && let Some(ty) = head_name(decl)
+                {
+                    let ctors: Vec<_> = items
+                        .iter()
+                        .filter_map(|item| head_name(strip_annotation(item)))
+                        .collect();
+                    for ctor in &ctors {
+                        self.ctors.insert(ctor.clone(), ty.clone());
+                    }
+                    self.types.insert(ty, ctors);
+                }
+                self.collect_expr(func);
+                self.collect_expr(arg);
+            }
+            Expr::Tuple(exprs, _) | Expr::List(exprs, _) | Expr::Block(exprs, _) => {
+                for expr in exprs {
+                    self.collect_expr(expr);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the name an expression applies, e.g. `Cons` for `Cons x xs`.
+fn head_name(expr: &Expr) -> Option<String> {
+    match expr.as_application().0 {
+        Expr::Atom(AtomKind::Name(name), _) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Returns the pattern without its type annotation, if any, as in `pat: Type`.
+fn strip_annotation(pattern: &Expr) -> &Expr {
+    let (head, args) = pattern.as_application();
+    match (head, args.as_slice()) {
+        (Expr::Atom(AtomKind::Name(op), _), [pattern, _]) if op == ":" => strip_annotation(pattern),
+        _ => pattern,
+    }
+}
+
+/// Checks whether a pattern matches every value,
+/// i.e. it is the wildcard or a name (other than `true`, `false`, and constructors),
+/// possibly annotated with a type or among alternatives separated by `|`.
+fn is_catch_all(pattern: &Expr, data: &DataTable) -> bool {
+    match strip_annotation(pattern) {
+        Expr::Atom(AtomKind::Wildcard, _) => true,
+        Expr::Atom(AtomKind::Name(name), _) => {
+            name != "true" && name != "false" && !data.ctors.contains_key(name)
+        }
+        pattern @ Expr::App(..) => {
+            let (head, args) = pattern.as_application();
+            match (head, args.as_slice()) {
+                (Expr::Atom(AtomKind::Name(op), _), [lhs, rhs]) if op == "|" => {
+                    is_catch_all(lhs, data) || is_catch_all(rhs, data)
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Collects the constructors a pattern starts with, among alternatives separated by `|`,
+/// each paired with whether it is applied only to patterns matching every value,
+/// e.g. `Cons _ xs`, thus covering all values built with it.
+///
+/// Nested constructor patterns, e.g. `Cons x Nil`, are not analyzed
+/// and never cover their constructor.
+fn collect_ctors<'e>(pattern: &'e Expr, data: &DataTable, ctors: &mut Vec<(&'e str, bool)>) {
+    let (head, args) = strip_annotation(pattern).as_application();
+    if let Expr::Atom(AtomKind::Name(name), _) = head {
+        match args.as_slice() {
+            [lhs, rhs] if name == "|" => {
+                collect_ctors(lhs, data, ctors);
+                collect_ctors(rhs, data, ctors);
+            }
+            args if data.ctors.contains_key(name) => {
+                let in_full = args.iter().all(|arg| is_catch_all(arg, data));
+                ctors.push((name, in_full));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Warns about the alternatives of a `match` spanning `span` that follow
+/// an unguarded alternative matching every value,
+/// and about the constructors of a data type it fails to cover.
+///
+/// The data type is that of the first constructor any pattern starts with;
+/// a `match` whose patterns start with no known constructor is not checked.
+fn check_match(alts: &[Expr], span: Span, data: &DataTable, diags: &mut Diagnostics) {
+    let mut catch_all = false;
+    let mut ctors = Vec::new();
+    // Constructors covered by unguarded alternatives
+    let mut covered = Vec::new();
+
+    for alt in alts {
+        if catch_all {
+            diags.emit(Error(UnreachableAlt, alt.span()));
+            continue;
+        }
+        let (head, args) = alt.as_application();
+        if let (Expr::Atom(AtomKind::Name(op), _), [lhs, _]) = (head, args.as_slice())
+            && op == "=>"
+        {
+            let (pattern, guard) = split_guard(lhs);
+            let start = ctors.len();
+            collect_ctors(pattern, data, &mut ctors);
+            if guard.is_none() {
+                catch_all = is_catch_all(pattern, data);
+                covered.extend(
+                    ctors[start..]
+                        .iter()
+                        .filter(|(_, in_full)| *in_full)
+                        .map(|(ctor, _)| *ctor),
+                );
+            }
+        }
+    }
+
+    if !catch_all && let Some((ctor, _)) = ctors.first() {
+        let missing: Vec<_> = data.types[&data.ctors[*ctor]]
+            .iter()
+            .filter(|ctor| !covered.contains(&ctor.as_str()))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            diags.emit(Error(NonExhaustivePatterns(missing), span));
+        }
+    }
+}
+
+/// Reports the fields of a `rec` literal, i.e. `rec {k0 = a; k1 = b}`,
+/// or a `rec` update, i.e. `rec r {k0 = a}`,
+/// that are already given earlier in it.
+fn check_record(fields: &[Expr], diags: &mut Diagnostics) {
+    let mut seen = HashSet::new();
+    for field in fields {
+        let (head, args) = field.as_application();
+        if let (Expr::Atom(AtomKind::Name(op), _), [Expr::Atom(AtomKind::Name(name), span), _]) =
+            (head, args.as_slice())
+            && op == "="
+            && !seen.insert(name)
+        {
+            diags.emit(Error(DuplicateField(name.clone()), *span));
+        }
+    }
+}
+
+/// Checks a single expression and its subexpressions,
+/// given the data types declared.
+fn validate_expr(expr: &Expr, data: &DataTable, diags: &mut Diagnostics) {
+    match expr {
+        Expr::Atom(..) => {}
+        Expr::App(func, arg, span) => {
+            let (head, args) = expr.as_application();
+            match (head, args.as_slice()) {
+                (Expr::Atom(AtomKind::Name(name), _), [_, Expr::Block(alts, _)])
+                    if name == "match" =>
+                {
+                    check_match(alts, *span, data, diags);
+                }
+                (
+                    Expr::Atom(AtomKind::Name(name), _),
+                    [Expr::Block(fields, _)] | [_, Expr::Block(fields, _)],
+                ) if name == "rec" => {
+                    check_record(fields, diags);
+                }
+                _ => {}
+            }
+            validate_expr(func, data, diags);
+            validate_expr(arg, data, diags);
+        }
+        Expr::Tuple(exprs, _) | Expr::List(exprs, _) | Expr::Block(exprs, _) => {
+            for expr in exprs {
+                validate_expr(expr, data, diags);
+            }
+        }
+    }
+}
+
+/// Checks parsed Lynx expressions for likely mistakes,
+/// emitting diagnostics into `diags`.
+///
+/// Currently, this warns about `match` alternatives that can never be reached
+/// because an earlier alternative matches every value,
+/// and about `match`es failing to cover some constructors of a data type
+/// declared with the `data` macro,
+/// and reports fields given more than once in a `rec` literal or update.
+pub fn validate(exprs: &[Expr], diags: &mut Diagnostics) {
+    let data = DataTable::collect(exprs);
+    for expr in exprs {
+        validate_expr(expr, &data, diags);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::tokenize,
+        parser::{ParserConfig, parse},
+        token::Pos,
+    };
+
+    fn validate_str(src: &str) -> Vec<(usize, usize)> {
+        let exprs = parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        let mut diags = Diagnostics::new();
+        validate(&exprs, &mut diags);
+        assert!(!diags.has_errors());
+        diags
+            .into_vec()
+            .into_iter()
+            .map(|Error(kind, Span(Pos(line, col), _))| {
+                assert!(matches!(kind, UnreachableAlt));
+                (line, col)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_unreachable_alt() {
+        let src = "match n {\n  1 => 'a';\n  _ => 'b';\n  2 => 'c';\n  x => 'd';\n}";
+        assert_eq!(validate_str(src), vec![(4, 3), (5, 3)]);
+
+        let src = "f = x => match x { (y: Int) | 0 => y; 1 => 0 }";
+        assert_eq!(validate_str(src), vec![(1, 39)]);
+    }
+
+    /// Returns the constructors reported missing by each warning about a `match`.
+    fn missing_ctors(src: &str) -> Vec<Vec<String>> {
+        let list = "data (List (A: Type)) {Nil; Cons @A (_: A, _: List A)};";
+        let exprs = parse(
+            &tokenize(&(list.to_string() + src)).unwrap(),
+            &ParserConfig::default(),
+        )
+        .unwrap();
+        let mut diags = Diagnostics::new();
+        validate(&exprs, &mut diags);
+        diags
+            .into_vec()
+            .into_iter()
+            .map(|Error(kind, _)| match kind {
+                NonExhaustivePatterns(missing) => missing,
+                kind => panic!("unexpected {:?}", kind),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exhaustive_match() {
+        for src in [
+            "match xs {Nil => 0; Cons x rest => x}",
+            "match xs {(Cons _ _ | Nil): List Int => 0}",
+            "match xs {Cons 1 rest => 1; Nil => 0; Cons x _ => x}",
+        ] {
+            assert!(missing_ctors(src).is_empty(), "validating {:?}", src);
+        }
+    }
+
+    #[test]
+    fn test_non_exhaustive_match() {
+        assert_eq!(
+            missing_ctors("match xs {Cons x rest => x}"),
+            vec![vec!["Nil"]]
+        );
+        // Guarded alternatives and nested patterns cover nothing
+        assert_eq!(
+            missing_ctors("match xs {Nil if (c) => 0; Cons x Nil => x}"),
+            vec![vec!["Nil", "Cons"]]
+        );
+        assert_eq!(
+            missing_ctors("f = xs => match xs {Nil => 0}"),
+            vec![vec!["Cons"]]
+        );
+
+        let src = "data (Expr (A: Type)) {\n  lit @A (_: A): Expr A;\n  neg (_: Expr Int): Expr Int;\n};\n\
+                   e = match x {\n  lit a => a;\n}";
+        let exprs = parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        let mut diags = Diagnostics::new();
+        validate(&exprs, &mut diags);
+        let diags = diags.into_vec();
+        let [Error(NonExhaustivePatterns(missing), Span(Pos(5, 5), _))] = diags.as_slice() else {
+            panic!("expected non-exhaustive patterns, got {:?}", diags);
+        };
+        assert_eq!(missing, &vec!["neg".to_string()]);
+    }
+
+    #[test]
+    fn test_catch_all_is_exhaustive() {
+        for src in [
+            "match xs {Nil => 0; _ => 1}",
+            "match xs {Cons x _ => x; rest => 0}",
+            "match xs {(ys: List Int) => 0}",
+            // Patterns starting with no known constructor are not checked
+            "match n {0 => 'a'; 1 => 'b'}",
+        ] {
+            assert!(missing_ctors(src).is_empty(), "validating {:?}", src);
+        }
+        // A constructor is not a catch-all once declared
+        let src = "data (Bit) {Zero; One}; match b {Zero => 0; One => 1}";
+        assert!(validate_str(src).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_field() {
+        let src = "p = rec {x = 1; y = 2; x = 3}";
+        let exprs = parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        let mut diags = Diagnostics::new();
+        validate(&exprs, &mut diags);
+        let diags = diags.into_vec();
+        assert_eq!(diags.len(), 1);
+        assert!(matches!(&diags[0].0, DuplicateField(name) if name == "x"));
+        assert_eq!(diags[0].1, Span(Pos(1, 24), Pos(1, 24)));
+
+        // Field order is insignificant
+        assert!(validate_str("rec {y = 2; x = 1}; rec {x = 1; y = 2}").is_empty());
+    }
+
+    #[test]
+    fn test_record_update() {
+        assert!(validate_str("rec r {x = 5}").is_empty());
+        assert!(validate_str("rec r {p = rec q {y = 2}; x = 1}").is_empty());
+
+        let src = "rec r {p = rec q {y = 2; y = 3}}";
+        let exprs = parse(&tokenize(src).unwrap(), &ParserConfig::default()).unwrap();
+        let mut diags = Diagnostics::new();
+        validate(&exprs, &mut diags);
+        let diags = diags.into_vec();
+        assert_eq!(diags.len(), 1);
+        assert!(matches!(&diags[0].0, DuplicateField(name) if name == "y"));
+        assert_eq!(diags[0].1, Span(Pos(1, 26), Pos(1, 26)));
+    }
+
+    #[test]
+    fn test_reachable_alts() {
+        let src = "match n {\n  true => 1;\n  (0, y) => y;\n  [x] => x;\n  x if (x > 0) => x;\n  0 => 0;\n}";
+        assert!(validate_str(src).is_empty());
+    }
+}
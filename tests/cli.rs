@@ -0,0 +1,126 @@
+use std::{path::PathBuf, process::Command};
+
+/// Writes `src` to a temporary file named after the test
+/// and runs the CLI on it with `args`,
+/// returning whether it succeeded, stdout, and stderr.
+fn run_with_status(name: &str, src: &str, args: &[&str]) -> (bool, String, String) {
+    let path: PathBuf = std::env::temp_dir().join(format!("lynx-cli-{}.lx", name));
+    std::fs::write(&path, src).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_lynx-lang"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// Runs the CLI like [`run_with_status`], asserting that it succeeds.
+fn run(name: &str, src: &str, args: &[&str]) -> (String, String) {
+    let (success, stdout, stderr) = run_with_status(name, src, args);
+    assert!(success);
+    (stdout, stderr)
+}
+
+#[test]
+fn test_time() {
+    let src = "x = 1 + 2; x * 3";
+    let (stdout, stderr) = run("time", src, &["--eval"]);
+    let (timed_stdout, timed_stderr) = run("time-on", src, &["--eval", "--time"]);
+
+    assert_eq!(stdout, "9\n");
+    assert_eq!(timed_stdout, stdout);
+    assert!(stderr.is_empty());
+
+    let lines: Vec<_> = timed_stderr.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("lex: "));
+    assert!(lines[1].starts_with("parse: "));
+}
+
+#[test]
+fn test_script() {
+    let src = "x = 1 + 2\n\nx * 3\n\n\"done\"";
+    let (stdout, stderr) = run("script", src, &["--script"]);
+    assert_eq!(stdout, "9\n\"done\"\n");
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn test_run() {
+    let src = "double = x => x * 2\n\nmain = () => double 21";
+    let (stdout, stderr) = run("run", src, &["--run"]);
+    assert_eq!(stdout, "42\n");
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn test_emit_tokens_json() {
+    let (stdout, stderr) = run("tokens-json", "f \"a\\\"λ\"", &["--emit-tokens-json"]);
+    assert_eq!(
+        stdout,
+        concat!(
+            r#"{"version":1,"tokens":["#,
+            r#"{"kind":"Name","start":{"line":1,"col":1,"offset":0},"#,
+            r#""end":{"line":1,"col":1,"offset":0},"text":"f"},"#,
+            r#"{"kind":"StrLit","start":{"line":1,"col":3,"offset":2},"#,
+            r#""end":{"line":1,"col":8,"offset":8},"text":"\"a\\\"λ\""}"#,
+            "]}\n"
+        )
+    );
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn test_dump_ast() {
+    let (stdout, stderr) = run("dump-ast", "f x;\n-1", &["--dump-ast"]);
+    assert_eq!(
+        stdout,
+        "\
+App [1:1, 1:3]
+  Name(\"f\") [1:1, 1:1]
+  Name(\"x\") [1:3, 1:3]
+App [2:1, 2:2]
+  Name(\"negate\") [2:1, 2:1]
+  IntLit(1) [2:2, 2:2]
+"
+    );
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn test_max_errors() {
+    let src = "1;;\n".repeat(50) + "2";
+    let (stdout, stderr) = run("max-errors", &src, &["--eval", "--max-errors", "5"]);
+    assert_eq!(stdout, "2\n");
+    let reported = stderr
+        .lines()
+        .filter(|line| line.starts_with("Warning: "))
+        .count();
+    assert_eq!(reported, 5);
+    assert!(stderr.ends_with("... and 45 more\n"));
+}
+
+#[test]
+fn test_max_errors_keeps_errors() {
+    // The only error comes after the cap is full of warnings
+    let src = "1;;\n".repeat(10) + ")";
+    let (success, stdout, stderr) = run_with_status(
+        "max-errors-keeps-errors",
+        &src,
+        &["--eval", "--max-errors", "3"],
+    );
+    assert!(!success);
+    assert!(stdout.is_empty());
+    let headers: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.starts_with("Warning: ") || line.starts_with("Error: "))
+        .collect();
+    assert_eq!(headers.len(), 3);
+    assert!(headers[2].starts_with("Error: "));
+    assert!(stderr.ends_with("... and 8 more\n"));
+}
@@ -0,0 +1,103 @@
+//! Golden-file tests of the lexer and the parser.
+//!
+//! Each `tests/golden/<name>.lynx` is lexed and parsed,
+//! and the output is compared against `<name>.tokens` and `<name>.ast` next to it.
+//! Run with `LYNX_BLESS=1` to write the current output to the golden files instead,
+//! e.g. after adding a case or changing the output on purpose.
+
+use std::{fmt::Write, fs, path::Path};
+
+use lynx::{
+    lexer::tokenize,
+    parser::{ParserConfig, parse},
+};
+
+/// Renders the tokens of `src`, one per line.
+fn render_tokens(src: &str) -> String {
+    match tokenize(src) {
+        Ok(tokens) => tokens.iter().fold(String::new(), |mut out, token| {
+            writeln!(out, "{}", token).unwrap();
+            out
+        }),
+        Err(err) => format!("{}\n", err),
+    }
+}
+
+/// Renders the top-level expressions of `src`, one per line.
+fn render_ast(src: &str) -> String {
+    let result = tokenize(src).and_then(|tokens| parse(&tokens, &ParserConfig::default()));
+    match result {
+        Ok(exprs) => exprs.iter().fold(String::new(), |mut out, expr| {
+            writeln!(out, "{}", expr).unwrap();
+            out
+        }),
+        Err(err) => format!("{}\n", err),
+    }
+}
+
+/// Renders the lines that differ between `expected` and `actual`,
+/// prefixed with their line numbers and `-` or `+` respectively.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let mut out = String::new();
+    for idx in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(idx), actual.get(idx));
+        if old == new {
+            continue;
+        }
+        if let Some(old) = old {
+            writeln!(out, "{:>4} - {}", idx + 1, old).unwrap();
+        }
+        if let Some(new) = new {
+            writeln!(out, "{:>4} + {}", idx + 1, new).unwrap();
+        }
+    }
+    out
+}
+
+/// Compares `actual` against the golden file at `path`,
+/// or overwrites the latter if blessing,
+/// returning a report of the mismatch, if any.
+fn check(path: &Path, actual: &str, bless: bool) -> Option<String> {
+    if bless {
+        fs::write(path, actual).unwrap();
+        return None;
+    }
+    let expected = fs::read_to_string(path).unwrap_or_default();
+    (expected != actual).then(|| format!("{}:\n{}", path.display(), diff(&expected, actual)))
+}
+
+#[test]
+fn test_golden() {
+    let bless = std::env::var_os("LYNX_BLESS").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut inputs: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lynx"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "no golden cases in {}", dir.display());
+
+    let mut mismatches = Vec::new();
+    for input in &inputs {
+        let src = fs::read_to_string(input).unwrap();
+        mismatches.extend(check(
+            &input.with_extension("tokens"),
+            &render_tokens(&src),
+            bless,
+        ));
+        mismatches.extend(check(
+            &input.with_extension("ast"),
+            &render_ast(&src),
+            bless,
+        ));
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "golden files differ (rerun with LYNX_BLESS=1 to update them):\n\n{}",
+        mismatches.join("\n")
+    );
+}